@@ -1,15 +1,32 @@
 use super::*;
 use log::{debug, warn};
-use serde_json::{self, Value};
+use serde::de::DeserializeOwned;
+use serde_json::{self, json, Value};
+use std::any::{Any, TypeId};
 use std::collections::HashMap;
-use std::io::prelude::*;
+use std::convert::TryFrom;
+use std::future::Future;
 use std::iter::Iterator;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::time::Instant;
+
+tokio::task_local! {
+    /// Per-call override of [Mpv]'s default command timeout, see [with_command_timeout].
+    static COMMAND_TIMEOUT: Option<Duration>;
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PlaylistEntry {
+    /// The position of the entry in the playlist.
+    #[serde(default)]
     pub id: usize,
     pub filename: String,
+    #[serde(default)]
     pub title: String,
+    #[serde(default)]
     pub current: bool,
 }
 
@@ -18,24 +35,26 @@ pub trait TypeHandler: Sized {
     fn as_string(&self) -> String;
 }
 
+/// Returns the `data` of a reply, or the error mpv reported instead.
+fn reply_data(value: Value) -> Result<Value, Error> {
+    let mut map = match value {
+        Value::Object(map) => map,
+        _ => return Err(Error(ErrorCode::UnexpectedValue)),
+    };
+    match map.get("error") {
+        Some(Value::String(error)) if error == "success" => {
+            Ok(map.remove("data").unwrap_or(Value::Null))
+        }
+        Some(Value::String(error)) => Err(mpv_error(error, None)),
+        _ => Err(Error(ErrorCode::UnexpectedValue)),
+    }
+}
+
 impl TypeHandler for String {
     fn get_value(value: Value) -> Result<String, Error> {
-        if let Value::Object(map) = value {
-            if let Value::String(ref error) = map["error"] {
-                if error == "success" && map.contains_key("data") {
-                    if let Value::String(ref s) = map["data"] {
-                        Ok(s.to_string())
-                    } else {
-                        Err(Error(ErrorCode::ValueDoesNotContainString))
-                    }
-                } else {
-                    Err(Error(ErrorCode::MpvError(error.to_string())))
-                }
-            } else {
-                Err(Error(ErrorCode::UnexpectedValue))
-            }
-        } else {
-            Err(Error(ErrorCode::UnexpectedValue))
+        match reply_data(value)? {
+            Value::String(s) => Ok(s),
+            _ => Err(Error(ErrorCode::ValueDoesNotContainString)),
         }
     }
 
@@ -46,24 +65,11 @@ impl TypeHandler for String {
 
 impl TypeHandler for bool {
     fn get_value(value: Value) -> Result<bool, Error> {
-        if let Value::Object(map) = value {
-            if let Value::String(ref error) = map["error"] {
-                if error == "success" && map.contains_key("data") {
-                    if let Value::Bool(ref b) = map["data"] {
-                        Ok(*b)
-                    } else {
-                        Err(Error(ErrorCode::ValueDoesNotContainBool))
-                    }
-                } else {
-                    Err(Error(ErrorCode::MpvError(error.to_string())))
-                }
-            } else {
-                Err(Error(ErrorCode::UnexpectedValue))
-            }
-        } else {
-            Err(Error(ErrorCode::UnexpectedValue))
-        }
+        reply_data(value)?
+            .as_bool()
+            .ok_or(Error(ErrorCode::ValueDoesNotContainBool))
     }
+
     fn as_string(&self) -> String {
         if *self {
             "true".to_string()
@@ -75,23 +81,9 @@ impl TypeHandler for bool {
 
 impl TypeHandler for f64 {
     fn get_value(value: Value) -> Result<f64, Error> {
-        if let Value::Object(map) = value {
-            if let Value::String(ref error) = map["error"] {
-                if error == "success" && map.contains_key("data") {
-                    if let Value::Number(ref num) = map["data"] {
-                        Ok(num.as_f64().unwrap())
-                    } else {
-                        Err(Error(ErrorCode::ValueDoesNotContainF64))
-                    }
-                } else {
-                    Err(Error(ErrorCode::MpvError(error.to_string())))
-                }
-            } else {
-                Err(Error(ErrorCode::UnexpectedValue))
-            }
-        } else {
-            Err(Error(ErrorCode::UnexpectedValue))
-        }
+        reply_data(value)?
+            .as_f64()
+            .ok_or(Error(ErrorCode::ValueDoesNotContainF64))
     }
 
     fn as_string(&self) -> String {
@@ -101,23 +93,70 @@ impl TypeHandler for f64 {
 
 impl TypeHandler for usize {
     fn get_value(value: Value) -> Result<usize, Error> {
-        if let Value::Object(map) = value {
-            if let Value::String(ref error) = map["error"] {
-                if error == "success" && map.contains_key("data") {
-                    if let Value::Number(ref num) = map["data"] {
-                        Ok(num.as_u64().unwrap() as usize)
-                    } else {
-                        Err(Error(ErrorCode::ValueDoesNotContainUsize))
-                    }
-                } else {
-                    Err(Error(ErrorCode::MpvError(error.to_string())))
-                }
-            } else {
-                Err(Error(ErrorCode::UnexpectedValue))
-            }
-        } else {
-            Err(Error(ErrorCode::UnexpectedValue))
-        }
+        json_usize(&reply_data(value)?).ok_or(Error(ErrorCode::ValueDoesNotContainUsize))
+    }
+
+    fn as_string(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl TypeHandler for f32 {
+    fn get_value(value: Value) -> Result<f32, Error> {
+        reply_data(value)?
+            .as_f64()
+            .map(|n| n as f32)
+            .ok_or(Error(ErrorCode::ValueDoesNotContainF32))
+    }
+
+    fn as_string(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl TypeHandler for i64 {
+    fn get_value(value: Value) -> Result<i64, Error> {
+        reply_data(value)?
+            .as_i64()
+            .ok_or(Error(ErrorCode::ValueDoesNotContainI64))
+    }
+
+    fn as_string(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl TypeHandler for i32 {
+    fn get_value(value: Value) -> Result<i32, Error> {
+        reply_data(value)?
+            .as_i64()
+            .and_then(|n| i32::try_from(n).ok())
+            .ok_or(Error(ErrorCode::ValueDoesNotContainI32))
+    }
+
+    fn as_string(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl TypeHandler for u64 {
+    fn get_value(value: Value) -> Result<u64, Error> {
+        reply_data(value)?
+            .as_u64()
+            .ok_or(Error(ErrorCode::ValueDoesNotContainU64))
+    }
+
+    fn as_string(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl TypeHandler for u32 {
+    fn get_value(value: Value) -> Result<u32, Error> {
+        reply_data(value)?
+            .as_u64()
+            .and_then(|n| u32::try_from(n).ok())
+            .ok_or(Error(ErrorCode::ValueDoesNotContainU32))
     }
 
     fn as_string(&self) -> String {
@@ -127,23 +166,7 @@ impl TypeHandler for usize {
 
 impl TypeHandler for HashMap<String, MpvDataType> {
     fn get_value(value: Value) -> Result<HashMap<String, MpvDataType>, Error> {
-        if let Value::Object(map) = value {
-            if let Value::String(ref error) = map["error"] {
-                if error == "success" && map.contains_key("data") {
-                    if let Value::Object(ref inner_map) = map["data"] {
-                        Ok(json_map_to_hashmap(inner_map))
-                    } else {
-                        Err(Error(ErrorCode::ValueDoesNotContainHashMap))
-                    }
-                } else {
-                    Err(Error(ErrorCode::MpvError(error.to_string())))
-                }
-            } else {
-                Err(Error(ErrorCode::UnexpectedValue))
-            }
-        } else {
-            Err(Error(ErrorCode::UnexpectedValue))
-        }
+        json_hashmap(&reply_data(value)?).ok_or(Error(ErrorCode::ValueDoesNotContainHashMap))
     }
 
     fn as_string(&self) -> String {
@@ -153,22 +176,9 @@ impl TypeHandler for HashMap<String, MpvDataType> {
 
 impl TypeHandler for Vec<PlaylistEntry> {
     fn get_value(value: Value) -> Result<Vec<PlaylistEntry>, Error> {
-        if let Value::Object(map) = value {
-            if let Value::String(ref error) = map["error"] {
-                if error == "success" && map.contains_key("data") {
-                    if let Value::Array(ref playlist_vec) = map["data"] {
-                        Ok(json_array_to_playlist(playlist_vec))
-                    } else {
-                        Err(Error(ErrorCode::ValueDoesNotContainPlaylist))
-                    }
-                } else {
-                    Err(Error(ErrorCode::MpvError(error.to_string())))
-                }
-            } else {
-                Err(Error(ErrorCode::UnexpectedValue))
-            }
-        } else {
-            Err(Error(ErrorCode::UnexpectedValue))
+        match reply_data(value)? {
+            Value::Array(ref playlist) => Ok(json_array_to_playlist(playlist)),
+            _ => Err(Error(ErrorCode::ValueDoesNotContainPlaylist)),
         }
     }
 
@@ -177,22 +187,74 @@ impl TypeHandler for Vec<PlaylistEntry> {
     }
 }
 
+impl TypeHandler for MpvDataType {
+    fn get_value(value: Value) -> Result<MpvDataType, Error> {
+        Ok(json_to_mpv_data(&reply_data(value)?))
+    }
+
+    fn as_string(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
 pub async fn get_mpv_property<T: TypeHandler>(instance: &Mpv, property: &str) -> Result<T, Error> {
-    let ipc_string = format!("{{ \"command\": [\"get_property\",\"{}\"] }}\n", property);
-    let data = send_command_async(instance, &ipc_string).await;
-    T::get_value(data.data)
+    let command = json!(["get_property", property]);
+    let data = send_command_async(&instance.connection, &command).await?;
+    check_response(&data, "get_property", property)?;
+    T::get_value(json!({ "error": data.error, "data": data.data }))
 }
 
-pub async fn get_mpv_property_string(instance: &Mpv, property: &str) -> Result<String, Error> {
-    let ipc_string = format!("{{ \"command\": [\"get_property\",\"{}\"] }}\n", property);
-    let data = send_command_async(instance, &ipc_string).await;
-    if data.error == "success"{
-        Ok(data.data.to_string())
-    }else{
-        Err(Error(ErrorCode::MpvError(data.error.to_string())))
+/// Like [get_mpv_property], but decodes the value with serde, so any `T: DeserializeOwned` works.
+pub async fn get_mpv_property_deserialized<T: DeserializeOwned + 'static>(
+    instance: &Mpv,
+    property: &str,
+) -> Result<T, Error> {
+    let command = json!(["get_property", property]);
+    let data = send_command_async(&instance.connection, &command).await?;
+    check_response(&data, "get_property", property)?;
+    decode_property(data.data)
+}
+
+/// Decodes the types [TypeHandler] supports with it, so they keep failing with their
+/// `ValueDoesNotContain*` code and playlist entries keep their positions as ids.
+fn decode_property<T: DeserializeOwned + 'static>(data: Value) -> Result<T, Error> {
+    fn handled<U: TypeHandler + 'static>(data: Value) -> Result<Box<dyn Any>, Error> {
+        let value = U::get_value(json!({ "error": "success", "data": data }))?;
+        Ok(Box::new(value))
     }
+
+    let id = TypeId::of::<T>();
+    let decoded = if id == TypeId::of::<String>() {
+        handled::<String>(data)
+    } else if id == TypeId::of::<bool>() {
+        handled::<bool>(data)
+    } else if id == TypeId::of::<f64>() {
+        handled::<f64>(data)
+    } else if id == TypeId::of::<usize>() {
+        handled::<usize>(data)
+    } else if id == TypeId::of::<HashMap<String, MpvDataType>>() {
+        handled::<HashMap<String, MpvDataType>>(data)
+    } else if id == TypeId::of::<Vec<PlaylistEntry>>() {
+        handled::<Vec<PlaylistEntry>>(data)
+    } else if id == TypeId::of::<Playlist>() {
+        handled::<Vec<PlaylistEntry>>(data).map(|entries| {
+            let entries = entries.downcast::<Vec<PlaylistEntry>>().unwrap();
+            Box::new(Playlist(*entries)) as Box<dyn Any>
+        })
+    } else {
+        return serde_json::from_value(data)
+            .map_err(|why| Error(ErrorCode::JsonParseError(why.to_string())));
+    };
+    Ok(*decoded?.downcast::<T>().unwrap())
+}
+
+pub async fn get_mpv_property_string(instance: &Mpv, property: &str) -> Result<String, Error> {
+    let command = json!(["get_property", property]);
+    let data = send_command_async(&instance.connection, &command).await?;
+    check_response(&data, "get_property", property)?;
+    Ok(data.data.to_string())
     // if let Value::Object(map) = data.data {
-    //     if let Value::String(ref error) = map["error"] {
+    //     if let Some(Value::String(ref error)) = map.get("error") {
     //         if error == "success" && map.contains_key("data") {
     //             match map["data"] {
     //                 Value::Bool(b) => Ok(b.to_string()),
@@ -215,39 +277,21 @@ pub async fn get_mpv_property_string(instance: &Mpv, property: &str) -> Result<S
     // }
 }
 
-pub async fn set_mpv_property<T: TypeHandler>(
+pub async fn set_mpv_property<T: Serialize>(
     instance: &Mpv,
     property: &str,
     value: T,
 ) -> Result<(), Error> {
-    let ipc_string = format!(
-        "{{ \"command\": [\"set_property\", \"{}\", {}] }}\n",
-        property,
-        value.as_string()
-    );
-    // match serde_json::from_str::<Value>(&send_command_async(instance, &ipc_string).await) {
-    //     Ok(_) => Ok(()),
-    //     Err(why) => Err(Error(ErrorCode::JsonParseError(why.to_string()))),
-    // }
-    send_command_async(instance, &ipc_string).await;
-    Ok(())
+    let command = json!(["set_property", property, value]);
+    let data = send_command_async(&instance.connection, &command).await?;
+    check_response(&data, "set_property", property)
 }
 
-pub async fn run_mpv_command(instance: &Mpv, command: &str, args: &[&str]) -> Result<(), Error> {
-    let mut ipc_string = format!("{{ \"command\": [\"{}\"", command);
-    if !args.is_empty() {
-        for arg in args {
-            ipc_string.push_str(&format!(", \"{}\"", arg));
-        }
-    }
-    ipc_string.push_str("] }\n");
-    let data = send_command_async(instance, &ipc_string).await;
-    if data.error == "success" {
-        Ok(())
-    } else {
-        Err(Error(ErrorCode::MpvError(data.error)))
-    }
-    // match serde_json::from_str::<Value>(&send_command_async(instance, &ipc_string).await) {
+/// Runs the mpv command _<command>_, passing every argument as the JSON value it is.
+pub async fn run_mpv_command(instance: &Mpv, command: &str, args: &[Value]) -> Result<(), Error> {
+    let data = send_command_async(&instance.connection, &positional_command(command, args)).await?;
+    check_response(&data, "command", command)
+    // match serde_json::from_str::<Value>(&send_command_async(&instance.connection, &ipc_string).await) {
     //     Ok(feedback) => {
     //         if let Value::String(ref error) = feedback["error"] {
     //             if error == "success" {
@@ -263,18 +307,107 @@ pub async fn run_mpv_command(instance: &Mpv, command: &str, args: &[&str]) -> Re
     // }
 }
 
-pub async fn observe_mpv_property(instance: &Mpv, id: &isize, property: &str) -> Result<(), Error> {
-    let ipc_string = format!(
-        "{{ \"command\": [\"observe_property\", {}, \"{}\"] }}\n",
-        id, property
-    );
-    let data = send_command_async(instance, &ipc_string).await;
+/// Runs the mpv command _<command>_ and decodes the `data` field of its reply as `T`.
+pub async fn run_mpv_command_with_result<T: TypeHandler>(
+    instance: &Mpv,
+    command: &str,
+    args: &[Value],
+) -> Result<T, Error> {
+    let data = send_command_async(&instance.connection, &positional_command(command, args)).await?;
+    check_response(&data, "command", command)?;
+    T::get_value(json!({ "error": data.error, "data": data.data }))
+}
+
+/// Runs _<command>_ (`audio-add`, `sub-add` or `video-add`) and returns the id of the new track.
+///
+/// mpv versions that don't put the id into the reply get it from the newest external track
+/// of _<track_type>_ that was loaded from the file in the first of _<args>_.
+pub async fn add_mpv_track(
+    instance: &Mpv,
+    track_type: TrackType,
+    command: &str,
+    args: &[Value],
+) -> Result<usize, Error> {
+    let data = send_command_async(&instance.connection, &positional_command(command, args)).await?;
+    check_response(&data, "command", command)?;
+    if let Some(id) = data.data.get("track_id").and_then(Value::as_u64) {
+        return Ok(id as usize);
+    }
+    let file = args.first().and_then(Value::as_str);
+    let tracks: Vec<Track> = get_mpv_property_deserialized(instance, "track-list").await?;
+    tracks
+        .into_iter()
+        .filter(|track| {
+            file.is_some()
+                && track.track_type == track_type
+                && track.external
+                && track.external_filename.as_deref() == file
+        })
+        .map(|track| track.id)
+        .max()
+        .ok_or(Error(ErrorCode::UnexpectedResult))
+}
+
+/// Runs the mpv command _<name>_ using mpv's named argument syntax.
+pub async fn run_mpv_command_named(
+    instance: &Mpv,
+    name: &str,
+    args: HashMap<String, MpvDataType>,
+) -> Result<(), Error> {
+    let data = send_command_async(&instance.connection, &named_command(name, args)).await?;
+    check_response(&data, "command", name)
+}
+
+/// Fails with the [MpvErrorKind] mpv reported unless the request succeeded.
+/// The error names the _<action>_ and its _<target>_ (e.g. `set_property pause`).
+pub(crate) fn check_response(data: &Data, action: &str, target: &str) -> Result<(), Error> {
     if data.error == "success" {
         Ok(())
     } else {
-        Err(Error(ErrorCode::MpvError(data.error)))
+        let context = format!("{} {}", action, target);
+        Err(mpv_error(&data.error, Some(context)))
     }
-    // match serde_json::from_str::<Value>(&send_command_async(instance, &ipc_string).await) {
+}
+
+fn mpv_error(error: &str, context: Option<String>) -> Error {
+    Error(ErrorCode::MpvError {
+        kind: MpvErrorKind::from_mpv_error(error),
+        context,
+    })
+}
+
+pub(crate) fn positional_command(command: &str, args: &[Value]) -> Value {
+    let mut command = vec![json!(command)];
+    command.extend_from_slice(args);
+    Value::Array(command)
+}
+
+pub(crate) fn named_command(name: &str, args: HashMap<String, MpvDataType>) -> Value {
+    let mut command = serde_json::Map::new();
+    command.insert("name".to_string(), json!(name));
+    for (key, value) in args {
+        command.insert(key, json!(value));
+    }
+    Value::Object(command)
+}
+
+pub async fn observe_mpv_property(instance: &Mpv, id: &isize, property: &str) -> Result<(), Error> {
+    observe_property_on(&instance.connection, id, property).await
+}
+
+pub async fn unobserve_mpv_property(instance: &Mpv, id: &isize) -> Result<(), Error> {
+    unobserve_property_on(&instance.connection, id).await
+}
+
+pub(crate) async fn observe_property_on(
+    connection: &Arc<Connection>,
+    id: &isize,
+    property: &str,
+) -> Result<(), Error> {
+    let command = json!(["observe_property", id, property]);
+    let data = send_command_async(connection, &command).await?;
+    check_response(&data, "observe_property", property)
+    // match serde_json::from_str::<Value>(&send_command_async(&instance.connection, &ipc_string).await) {
     //     Ok(feedback) => {
     //         if let Value::String(ref error) = feedback["error"] {
     //             if error == "success" {
@@ -290,43 +423,112 @@ pub async fn observe_mpv_property(instance: &Mpv, id: &isize, property: &str) ->
     // }
 }
 
-fn try_convert_property(name: &str, id: isize, data: MpvDataType) -> Event {
+pub(crate) async fn unobserve_property_on(
+    connection: &Arc<Connection>,
+    id: &isize,
+) -> Result<(), Error> {
+    let command = json!(["unobserve_property", id]);
+    let data = send_command_async(connection, &command).await?;
+    check_response(&data, "unobserve_property", &id.to_string())
+}
+
+fn try_convert_property(name: &str, id: isize, data: &Value) -> Event {
+    let unknown = || Property::Unknown {
+        name: name.to_string(),
+        data: json_to_mpv_data(data),
+    };
     let property = match name {
-        "path" => match data {
-            MpvDataType::String(value) => Property::Path(Some(value)),
-            MpvDataType::Null => Property::Path(None),
-            _ => unimplemented!(),
-        },
-        "pause" => match data {
-            MpvDataType::Bool(value) => Property::Pause(value),
-            _ => unimplemented!(),
-        },
-        "playback-time" => match data {
-            MpvDataType::Double(value) => Property::PlaybackTime(Some(value)),
-            MpvDataType::Null => Property::PlaybackTime(None),
-            _ => unimplemented!(),
-        },
-        "duration" => match data {
-            MpvDataType::Double(value) => Property::Duration(Some(value)),
-            MpvDataType::Null => Property::Duration(None),
-            _ => unimplemented!(),
-        },
-        "metadata" => match data {
-            MpvDataType::HashMap(value) => Property::Metadata(Some(value)),
-            MpvDataType::Null => Property::Metadata(None),
-            _ => unimplemented!(),
-        },
+        "path" => optional(data, json_string).map(Property::Path),
+        "pause" => data.as_bool().map(Property::Pause),
+        "playback-time" => optional(data, Value::as_f64).map(Property::PlaybackTime),
+        "duration" => optional(data, Value::as_f64).map(Property::Duration),
+        "metadata" => optional(data, json_hashmap).map(Property::Metadata),
+        "volume" => data.as_f64().map(Property::Volume),
+        "mute" => data.as_bool().map(Property::Mute),
+        "speed" => data.as_f64().map(Property::Speed),
+        "playlist-pos" => optional_position(data).map(Property::PlaylistPos),
+        "playlist-count" => json_usize(data).map(Property::PlaylistCount),
+        "playlist" => data
+            .as_array()
+            .map(|a| Property::Playlist(json_array_to_playlist(a))),
+        "time-pos" => optional(data, Value::as_f64).map(Property::TimePos),
+        "percent-pos" => optional(data, Value::as_f64).map(Property::PercentPos),
+        "time-remaining" => optional(data, Value::as_f64).map(Property::TimeRemaining),
+        "chapter" => optional_position(data).map(Property::Chapter),
+        "track-list" => serde_json::from_value(data.clone())
+            .ok()
+            .map(Property::TrackList),
+        "aid" => track_id(data).map(Property::Aid),
+        "sid" => track_id(data).map(Property::Sid),
+        "vid" => track_id(data).map(Property::Vid),
+        "loop-file" => loop_mode(data).map(Property::LoopFile),
+        "loop-playlist" => loop_mode(data).map(Property::LoopPlaylist),
+        "idle-active" => data.as_bool().map(Property::IdleActive),
+        "eof-reached" => data.as_bool().map(Property::EofReached),
+        "core-idle" => data.as_bool().map(Property::CoreIdle),
+        "paused-for-cache" => data.as_bool().map(Property::PausedForCache),
+        "demuxer-cache-state" => optional(data, json_hashmap).map(Property::DemuxerCacheState),
+        "media-title" => optional(data, json_string).map(Property::MediaTitle),
+        "filename" => optional(data, json_string).map(Property::Filename),
+        "seeking" => data.as_bool().map(Property::Seeking),
         _ => {
             warn!("Property {} not implemented", name);
-            Property::Unknown {
-                name: name.to_string(),
-                data,
-            }
+            return Event::PropertyChange {
+                id,
+                property: unknown(),
+            };
+        }
+    };
+    let property = match property {
+        Some(property) => property,
+        None => {
+            warn!("Unexpected value for property {}: {}", name, data);
+            unknown()
         }
     };
     Event::PropertyChange { id, property }
 }
 
+/// Decodes a property that is `null` while unavailable.
+fn optional<T>(data: &Value, decode: impl Fn(&Value) -> Option<T>) -> Option<Option<T>> {
+    match *data {
+        Value::Null => Some(None),
+        ref value => decode(value).map(Some),
+    }
+}
+
+/// Decodes positions such as `playlist-pos`, which mpv reports as -1 if there is none.
+fn optional_position(data: &Value) -> Option<Option<usize>> {
+    match data.as_i64() {
+        Some(position) if position >= 0 => Some(Some(position as usize)),
+        Some(_) => Some(None),
+        None => optional(data, json_usize),
+    }
+}
+
+/// Decodes `aid`, `sid` and `vid`, which are `false` (or `"no"`) if no track is selected.
+fn track_id(data: &Value) -> Option<Option<usize>> {
+    match *data {
+        Value::Bool(false) | Value::Null => Some(None),
+        Value::String(ref s) if s == "no" => Some(None),
+        ref value => json_usize(value).map(Some),
+    }
+}
+
+fn loop_mode(data: &Value) -> Option<LoopMode> {
+    match *data {
+        Value::Bool(false) => Some(LoopMode::No),
+        Value::Bool(true) => Some(LoopMode::Inf),
+        Value::String(ref s) => match s.as_str() {
+            "no" => Some(LoopMode::No),
+            "inf" | "yes" => Some(LoopMode::Inf),
+            "force" => Some(LoopMode::Force),
+            _ => None,
+        },
+        ref value => json_usize(value).map(LoopMode::Times),
+    }
+}
+
 // pub async fn listen(instance: &Mpv) -> Result<Event, Error> {
 //     // if !instance.reader.fill_buf().map_err(|_|Error(ErrorCode::ConnectError(format!("Failed to fill the buffer!"))))?[..9].eq(b"{\"event\":"){
 //     //     // debug!("Message received isn't an event: {:#?}", String::from_utf8_lossy(instance.reader.buffer()));
@@ -342,269 +544,340 @@ fn try_convert_property(name: &str, id: isize, data: MpvDataType) -> Event {
 
 pub fn handle_event(response: &str) -> Result<Event, Error> {
     match serde_json::from_str::<Value>(response) {
-        Ok(e) => {
-            if let Value::String(ref name) = e["event"] {
-                let event: Event;
-                match name.as_str() {
-                    "shutdown" => {
-                        event = Event::Shutdown;
-                    }
-                    "start-file" => {
-                        event = Event::StartFile;
-                    }
-                    "file-loaded" => {
-                        event = Event::FileLoaded;
-                    }
-                    "seek" => {
-                        event = Event::Seek;
-                    }
-                    "playback-restart" => {
-                        event = Event::PlaybackRestart;
-                    }
-                    "idle" => {
-                        event = Event::Idle;
-                    }
-                    "tick" => {
-                        event = Event::Tick;
-                    }
-                    "video-reconfig" => {
-                        event = Event::VideoReconfig;
-                    }
-                    "audio-reconfig" => {
-                        event = Event::AudioReconfig;
-                    }
-                    "tracks-changed" => {
-                        event = Event::TracksChanged;
-                    }
-                    "track-switched" => {
-                        event = Event::TrackSwitched;
-                    }
-                    "pause" => {
-                        event = Event::Pause;
-                    }
-                    "unpause" => {
-                        event = Event::Unpause;
-                    }
-                    "metadata-update" => {
-                        event = Event::MetadataUpdate;
-                    }
-                    "chapter-change" => {
-                        event = Event::ChapterChange;
-                    }
-                    "end-file" => {
-                        event = Event::EndFile;
-                    }
-                    "property-change" => {
-                        let name: String;
-                        let id: isize;
-                        let data: MpvDataType;
-
-                        if let Value::String(ref n) = e["name"] {
-                            name = n.to_string();
-                        } else {
-                            return Err(Error(ErrorCode::JsonContainsUnexptectedType));
-                        }
-
-                        if let Value::Number(ref n) = e["id"] {
-                            id = n.as_i64().unwrap() as isize;
-                        } else {
-                            id = 0;
-                        }
-
-                        match e["data"] {
-                            Value::String(ref n) => {
-                                data = MpvDataType::String(n.to_string());
-                            }
-
-                            Value::Array(ref a) => {
-                                if name == *"playlist" {
-                                    data =
-                                        MpvDataType::Playlist(Playlist(json_array_to_playlist(a)));
-                                } else {
-                                    data = MpvDataType::Array(json_array_to_vec(a));
-                                }
-                            }
-
-                            Value::Bool(ref b) => {
-                                data = MpvDataType::Bool(*b);
-                            }
-
-                            Value::Number(ref n) => {
-                                if n.is_u64() {
-                                    data = MpvDataType::Usize(n.as_u64().unwrap() as usize);
-                                } else if n.is_f64() {
-                                    data = MpvDataType::Double(n.as_f64().unwrap());
-                                } else {
-                                    return Err(Error(ErrorCode::JsonContainsUnexptectedType));
-                                }
-                            }
-
-                            Value::Object(ref m) => {
-                                data = MpvDataType::HashMap(json_map_to_hashmap(m));
-                            }
-
-                            Value::Null => {
-                                data = MpvDataType::Null;
-                            }
-                        }
-
-                        event = try_convert_property(name.as_ref(), id, data);
-                    }
-                    _ => {
-                        event = Event::Unimplemented;
-                    }
-                };
-                return Ok(event);
-            }
-        }
-        Err(why) => return Err(Error(ErrorCode::JsonParseError(why.to_string()))),
+        Ok(e) => decode_event(&e),
+        Err(why) => Err(Error(ErrorCode::JsonParseError(why.to_string()))),
     }
-    Ok(Event::Unimplemented)
-}
-
-pub fn listen_raw(instance: &mut Mpv) -> String {
-    let mut response = String::new();
-    instance.reader.read_line(&mut response).unwrap();
-    response.trim_end().to_string()
-    // let mut stream = &instance.0;
-    // let mut buffer = [0; 32];
-    // stream.read(&mut buffer[..]).unwrap();
-    // String::from_utf8_lossy(&buffer).into_owned()
-}
-
-async fn send_command_async(instance: &Mpv, command: &str) -> Data {
-    let mut stream = &instance.stream;
-    match stream.write_all(command.as_bytes()) {
-        Err(why) => panic!("Error: Could not write to socket: {}", why),
-        Ok(_) => {
-            debug!("Command: {}", command.trim_end());
-            let response = &mut *instance
-                .response_receiver
-                .lock()
-                .await;
-            let response = response
-                .recv()
-                .await
-                .unwrap();
-            // let mut response = String::new();
-            // {
-            //     let mut reader = BufReader::new(stream);
-            //     while !response.contains("\"error\":") {
-            //         response.clear();
-            //         reader.read_line(&mut response).unwrap();
-            //         // trace!("Response: {:#?}", response);
-            //     }
-            // }
-            debug!("Response: {:?}", response);
-            response
-        }
-    }
-}
-
-pub async fn send_flat_command(instance: &Mpv, command: &str) -> Result<(), Box<dyn std::error::Error>>{
-    let mut stream = &instance.stream;
-    debug!("Flat Command: {:#?}", command.trim_end());
-    Ok(stream.write_all(command.as_bytes())?)
 }
 
-fn json_map_to_hashmap(map: &serde_json::map::Map<String, Value>) -> HashMap<String, MpvDataType> {
-    let mut output_map: HashMap<String, MpvDataType> = HashMap::new();
-    for (ref key, value) in map.iter() {
-        match *value {
-            Value::Array(ref array) => {
-                output_map.insert(
-                    key.to_string(),
-                    MpvDataType::Array(json_array_to_vec(array)),
-                );
+/// Decodes an event mpv sent, including the payload of the events that carry one.
+pub(crate) fn decode_event(e: &Value) -> Result<Event, Error> {
+    if let Value::String(ref name) = e["event"] {
+        let event: Event;
+        match name.as_str() {
+            "shutdown" => {
+                event = Event::Shutdown;
             }
-            Value::Bool(ref b) => {
-                output_map.insert(key.to_string(), MpvDataType::Bool(*b));
+            "start-file" => {
+                event = start_file_event(e);
             }
-            Value::Number(ref n) => {
-                if n.is_u64() {
-                    output_map.insert(
-                        key.to_string(),
-                        MpvDataType::Usize(n.as_u64().unwrap() as usize),
-                    );
-                } else if n.is_f64() {
-                    output_map.insert(key.to_string(), MpvDataType::Double(n.as_f64().unwrap()));
-                } else {
-                    panic!("unimplemented number");
-                }
+            "file-loaded" => {
+                event = Event::FileLoaded;
             }
-            Value::String(ref s) => {
-                output_map.insert(key.to_string(), MpvDataType::String(s.to_string()));
+            "seek" => {
+                event = Event::Seek;
             }
-            Value::Object(ref m) => {
-                output_map.insert(
-                    key.to_string(),
-                    MpvDataType::HashMap(json_map_to_hashmap(m)),
-                );
+            "playback-restart" => {
+                event = Event::PlaybackRestart;
             }
-            Value::Null => {
-                unimplemented!();
+            "idle" => {
+                event = Event::Idle;
             }
-        }
-    }
-    output_map
-}
-
-fn json_array_to_vec(array: &[Value]) -> Vec<MpvDataType> {
-    let mut output: Vec<MpvDataType> = Vec::new();
-    if !array.is_empty() {
-        match array[0] {
-            Value::Array(_) => {
-                for entry in array {
-                    if let Value::Array(ref a) = *entry {
-                        output.push(MpvDataType::Array(json_array_to_vec(a)));
-                    }
-                }
+            "tick" => {
+                event = Event::Tick;
             }
-
-            Value::Bool(_) => {
-                for entry in array {
-                    if let Value::Bool(ref b) = *entry {
-                        output.push(MpvDataType::Bool(*b));
-                    }
-                }
+            "video-reconfig" => {
+                event = Event::VideoReconfig;
             }
-
-            Value::Number(_) => {
-                for entry in array {
-                    if let Value::Number(ref n) = *entry {
-                        if n.is_u64() {
-                            output.push(MpvDataType::Usize(n.as_u64().unwrap() as usize));
-                        } else if n.is_f64() {
-                            output.push(MpvDataType::Double(n.as_f64().unwrap()));
-                        } else {
-                            panic!("unimplemented number");
-                        }
-                    }
-                }
+            "audio-reconfig" => {
+                event = Event::AudioReconfig;
+            }
+            "tracks-changed" => {
+                event = Event::TracksChanged;
+            }
+            "track-switched" => {
+                event = Event::TrackSwitched;
+            }
+            "pause" => {
+                event = Event::Pause;
+            }
+            "unpause" => {
+                event = Event::Unpause;
+            }
+            "metadata-update" => {
+                event = Event::MetadataUpdate;
+            }
+            "chapter-change" => {
+                event = Event::ChapterChange;
+            }
+            "end-file" => {
+                event = end_file_event(e);
+            }
+            "log-message" => {
+                event = Event::LogMessage {
+                    prefix: json_string(&e["prefix"]).unwrap_or_default(),
+                    level: json_string(&e["level"]).unwrap_or_default(),
+                    text: json_string(&e["text"]).unwrap_or_default(),
+                };
             }
+            "client-message" => {
+                let args = match e["args"] {
+                    Value::Array(ref a) => a.iter().filter_map(json_string).collect(),
+                    _ => Vec::new(),
+                };
+                event = Event::ClientMessage { args };
+            }
+            "hook" => {
+                event = Event::Hook {
+                    name: json_string(&e["name"]),
+                    hook_id: e["hook_id"].as_u64(),
+                };
+            }
+            "get-property-reply" => {
+                event = Event::GetPropertyReply {
+                    id: e["id"].as_u64(),
+                    name: json_string(&e["name"]),
+                    error: json_string(&e["error"]),
+                };
+            }
+            "set-property-reply" => {
+                event = Event::SetPropertyReply {
+                    id: e["id"].as_u64(),
+                    error: json_string(&e["error"]),
+                };
+            }
+            "command-reply" => {
+                event = Event::CommandReply {
+                    id: e["id"].as_u64(),
+                    error: json_string(&e["error"]),
+                };
+            }
+            "property-change" => {
+                let name: String;
+                let id: isize;
 
-            Value::Object(_) => {
-                for entry in array {
-                    if let Value::Object(ref map) = *entry {
-                        output.push(MpvDataType::HashMap(json_map_to_hashmap(map)));
-                    }
+                if let Value::String(ref n) = e["name"] {
+                    name = n.to_string();
+                } else {
+                    return Err(Error(ErrorCode::JsonContainsUnexptectedType));
                 }
-            }
 
-            Value::String(_) => {
-                for entry in array {
-                    if let Value::String(ref s) = *entry {
-                        output.push(MpvDataType::String(s.to_string()));
-                    }
+                if let Value::Number(ref n) = e["id"] {
+                    id = n.as_i64().unwrap_or(0) as isize;
+                } else {
+                    id = 0;
                 }
-            }
 
-            Value::Null => {
-                unimplemented!();
+                event = try_convert_property(name.as_ref(), id, &e["data"]);
+            }
+            _ => {
+                event = Event::Other {
+                    name: name.to_string(),
+                    raw: e.clone(),
+                };
             }
+        };
+        return Ok(event);
+    }
+    Err(Error(ErrorCode::JsonContainsUnexptectedType))
+}
+
+pub(crate) fn start_file_event(event: &Value) -> Event {
+    Event::StartFile {
+        playlist_entry_id: json_usize(&event["playlist_entry_id"]),
+    }
+}
+
+pub(crate) fn end_file_event(event: &Value) -> Event {
+    let reason = match event["reason"].as_str() {
+        Some("eof") => EndFileReason::Eof,
+        Some("stop") => EndFileReason::Stop,
+        Some("quit") => EndFileReason::Quit,
+        Some("error") => EndFileReason::Error,
+        Some("redirect") => EndFileReason::Redirect,
+        _ => EndFileReason::Unknown,
+    };
+    Event::EndFile {
+        reason,
+        playlist_entry_id: json_usize(&event["playlist_entry_id"]),
+        file_error: event["file_error"].as_str().map(str::to_string),
+        playlist_insert_id: json_usize(&event["playlist_insert_id"]),
+        playlist_insert_num_entries: json_usize(&event["playlist_insert_num_entries"]),
+    }
+}
+
+fn json_usize(value: &Value) -> Option<usize> {
+    value.as_u64().map(|n| n as usize)
+}
+
+fn json_string(value: &Value) -> Option<String> {
+    value.as_str().map(str::to_string)
+}
+
+fn json_hashmap(value: &Value) -> Option<HashMap<String, MpvDataType>> {
+    value.as_object().map(json_map_to_hashmap)
+}
+
+/// Removes a request from the pending table once its caller stops waiting,
+/// whether the reply arrived or the future was dropped early.
+pub(crate) struct PendingRequestGuard {
+    connection: Arc<Connection>,
+    request_id: u32,
+}
+
+impl Drop for PendingRequestGuard {
+    fn drop(&mut self) {
+        self.connection
+            .pending_requests
+            .lock()
+            .unwrap()
+            .remove(&self.request_id);
+    }
+}
+
+fn next_request_id(connection: &Connection) -> u32 {
+    // mpv uses 0 for requests that did not specify an id, so never hand it out.
+    loop {
+        let id = connection.next_request_id.fetch_add(1, Ordering::Relaxed);
+        if id != 0 {
+            return id;
         }
     }
-    output
+}
+
+/// Runs `future` with every command it sends to mpv waiting at most _<timeout>_ for its reply.
+pub(crate) async fn with_command_timeout<F: Future>(timeout: Duration, future: F) -> F::Output {
+    COMMAND_TIMEOUT.scope(Some(timeout), future).await
+}
+
+/// Sends `command` (either an array of the command name followed by its arguments, or an
+/// object of named arguments) to mpv and waits for the reply carrying the same `request_id`.
+///
+/// Fails with [ErrorCode::Timeout] if no reply arrived within the command timeout.
+async fn send_command_async(connection: &Arc<Connection>, command: &Value) -> Result<Data, Error> {
+    let deadline = COMMAND_TIMEOUT
+        .try_with(|timeout| *timeout)
+        .unwrap_or(connection.command_timeout)
+        .map(|timeout| Instant::now() + timeout);
+    let _permit = match before(deadline, connection.in_flight_requests.acquire()).await? {
+        Ok(permit) => permit,
+        Err(why) => return Err(Error(ErrorCode::ConnectError(why.to_string()))),
+    };
+    // The write itself is not timed out: giving up halfway through would leave a partial
+    // request on the socket that garbles every later one.
+    let (_guard, receiver) = start_command(connection, command, false).await?;
+    match before(deadline, receiver).await? {
+        Ok(response) => {
+            debug!("Response: {:?}", response);
+            Ok(response)
+        }
+        Err(_) => Err(connection_closed()),
+    }
+}
+
+/// Waits for `future`, failing with [ErrorCode::Timeout] once _<deadline>_ has passed.
+async fn before<F: Future>(deadline: Option<Instant>, future: F) -> Result<F::Output, Error> {
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline, future)
+            .await
+            .map_err(|_| Error(ErrorCode::Timeout)),
+        None => Ok(future.await),
+    }
+}
+
+/// Sends `command` with `"async": true`. mpv only replies once the command has finished,
+/// so the reply is awaited through the returned handle instead of blocking the caller.
+pub(crate) async fn start_async_command(
+    connection: &Arc<Connection>,
+    command: &Value,
+) -> Result<AsyncCommand, Error> {
+    let (guard, receiver) = start_command(connection, command, true).await?;
+    let name = match command {
+        Value::Array(ref command) => command.first(),
+        _ => command.get("name"),
+    };
+    Ok(AsyncCommand {
+        command: name.and_then(Value::as_str).unwrap_or_default().to_string(),
+        receiver,
+        _guard: guard,
+    })
+}
+
+/// Registers a waiter for a new `request_id` and writes the command to the socket.
+async fn start_command(
+    connection: &Arc<Connection>,
+    command: &Value,
+    is_async: bool,
+) -> Result<(PendingRequestGuard, oneshot::Receiver<Data>), Error> {
+    let request_id = next_request_id(connection);
+    let mut ipc_string = match serde_json::to_string(&Request {
+        command,
+        request_id,
+        is_async,
+    }) {
+        Ok(request) => request,
+        Err(why) => return Err(Error(ErrorCode::JsonParseError(why.to_string()))),
+    };
+    ipc_string.push('\n');
+    let (sender, receiver) = oneshot::channel();
+    connection
+        .pending_requests
+        .lock()
+        .unwrap()
+        .insert(request_id, sender);
+    let guard = PendingRequestGuard {
+        connection: connection.clone(),
+        request_id,
+    };
+
+    if let Err(why) = write_to_socket(connection, &ipc_string).await {
+        return Err(Error(ErrorCode::ConnectError(why.to_string())));
+    }
+    debug!("Command: {}", ipc_string.trim_end());
+    Ok((guard, receiver))
+}
+
+pub(crate) fn connection_closed() -> Error {
+    Error(ErrorCode::ConnectError(
+        "Connection closed before mpv replied".to_string(),
+    ))
+}
+
+pub async fn send_flat_command(instance: &Mpv, command: &str) -> Result<(), Box<dyn std::error::Error>>{
+    debug!("Flat Command: {:#?}", command.trim_end());
+    Ok(write_to_socket(&instance.connection, command).await?)
+}
+
+async fn write_to_socket(connection: &Connection, message: &str) -> std::io::Result<()> {
+    match connection.writer.lock().await.as_mut() {
+        Some(writer) => writer.write_all(message.as_bytes()).await,
+        None => Err(std::io::Error::new(
+            std::io::ErrorKind::NotConnected,
+            "Not connected to mpv",
+        )),
+    }
+}
+
+fn json_to_mpv_data(value: &Value) -> MpvDataType {
+    match *value {
+        Value::String(ref s) => MpvDataType::String(s.to_string()),
+        Value::Array(ref a) => MpvDataType::Array(json_array_to_vec(a)),
+        Value::Bool(ref b) => MpvDataType::Bool(*b),
+        Value::Number(ref n) => json_number(n),
+        Value::Object(ref m) => MpvDataType::HashMap(json_map_to_hashmap(m)),
+        Value::Null => MpvDataType::Null,
+    }
+}
+
+/// Non-negative integers become [MpvDataType::Usize], negative ones [MpvDataType::Int64].
+fn json_number(n: &serde_json::Number) -> MpvDataType {
+    if let Some(n) = n.as_u64() {
+        MpvDataType::Usize(n as usize)
+    } else if let Some(n) = n.as_i64() {
+        MpvDataType::Int64(n)
+    } else {
+        MpvDataType::Double(n.as_f64().unwrap_or(f64::NAN))
+    }
+}
+
+fn json_map_to_hashmap(map: &serde_json::map::Map<String, Value>) -> HashMap<String, MpvDataType> {
+    map.iter()
+        .map(|(key, value)| (key.to_string(), json_to_mpv_data(value)))
+        .collect()
+}
+
+/// Decodes every element on its own, so arrays mixing types keep all of their elements.
+fn json_array_to_vec(array: &[Value]) -> Vec<MpvDataType> {
+    array.iter().map(json_to_mpv_data).collect()
 }
 
 fn json_array_to_playlist(array: &[Value]) -> Vec<PlaylistEntry> {
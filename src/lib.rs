@@ -4,22 +4,45 @@ use async_trait::async_trait;
 
 use ipc::*;
 use log::{debug, trace, warn};
-use serde_json::{Deserializer, Value};
-use std::collections::HashMap;
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
 use std::fmt::{self, Display};
-use std::io::{BufReader, Read};
-use std::os::unix::net::UnixStream;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicIsize, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll};
+use std::time::Duration;
 // use tokio::sync::broadcast::{Receiver, Sender};
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use serde::Serialize;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::UnixStream;
 use tokio::sync::mpsc::{Receiver, Sender};
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, oneshot, watch, Notify, Semaphore};
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::WatchStream;
+use tokio_stream::Stream;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Event {
     Shutdown,
-    StartFile,
-    EndFile,
+    StartFile {
+        playlist_entry_id: Option<usize>,
+    },
+    /// Playback of a file ended. `file_error` describes what went wrong if `reason` is
+    /// [EndFileReason::Error]; the `playlist_insert_*` fields are set on
+    /// [EndFileReason::Redirect], e.g. when a playlist file was expanded.
+    EndFile {
+        reason: EndFileReason,
+        playlist_entry_id: Option<usize>,
+        file_error: Option<String>,
+        playlist_insert_id: Option<usize>,
+        playlist_insert_num_entries: Option<usize>,
+    },
     FileLoaded,
     TracksChanged,
     TrackSwitched,
@@ -32,32 +55,115 @@ pub enum Event {
     MetadataUpdate,
     Seek,
     PlaybackRestart,
-    PropertyChange { id: isize, property: Property },
+    PropertyChange {
+        id: isize,
+        property: Property,
+    },
+    /// Sent by old mpv versions only and carries no data, observe [Property::Chapter] instead.
     ChapterChange,
-    Unimplemented,
+    /// The connection to mpv was lost, sent when reconnecting is enabled.
+    Disconnected,
+    /// The connection to mpv was re-established and the observed properties were registered again.
+    Reconnected,
+    /// A message logged by mpv, sent after `request_log_messages` was issued.
+    LogMessage {
+        prefix: String,
+        level: String,
+        text: String,
+    },
+    /// A message sent by a script or client through the `script-message` command.
+    ClientMessage {
+        args: Vec<String>,
+    },
+    /// A hook registered by this client was triggered; mpv waits until `hook-ack` is sent.
+    Hook {
+        name: Option<String>,
+        hook_id: Option<u64>,
+    },
+    GetPropertyReply {
+        id: Option<u64>,
+        name: Option<String>,
+        error: Option<String>,
+    },
+    SetPropertyReply {
+        id: Option<u64>,
+        error: Option<String>,
+    },
+    CommandReply {
+        id: Option<u64>,
+        error: Option<String>,
+    },
+    /// An event this library does not know about, or one whose payload could not be decoded.
+    Other {
+        name: String,
+        raw: Value,
+    },
+}
+
+/// Why playback of a file ended, see [Event::EndFile].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EndFileReason {
+    Eof,
+    Stop,
+    Quit,
+    Error,
+    Redirect,
+    Unknown,
 }
 
 impl From<MpvEvent> for Event {
     fn from(event: MpvEvent) -> Self {
-        match event.event.as_str() {
-            "pause" => Self::Pause,
-            "unpause" => Self::Unpause,
-            "shutdown" => Self::Shutdown,
-            "file_loaded" => Self::FileLoaded,
-            "tracks_changed" => Self::TracksChanged,
-            "track_switched" => Self::TrackSwitched,
-            "idle" => Self::Idle,
-            "seek" => Self::Seek,
-            "video_reconfig" => Self::VideoReconfig,
-            "audio_reconfig" => Self::AudioReconfig,
-            _ => {
-                trace!("Event {:#?} hasn't been implemented yet!", event.event);
-                Self::Unimplemented
+        let mut raw = event.data;
+        raw.insert("event".to_string(), Value::String(event.event.clone()));
+        let raw = Value::Object(raw);
+        match decode_event(&raw) {
+            Ok(event) => event,
+            Err(why) => {
+                warn!("Could not decode event {}: {}", event.event, why);
+                Self::Other {
+                    name: event.event,
+                    raw,
+                }
             }
         }
     }
 }
 
+impl Event {
+    /// The name mpv uses for this event, e.g. `"property-change"`.
+    /// [Event::Disconnected] and [Event::Reconnected] are named `"disconnected"` and `"reconnected"`.
+    pub fn name(&self) -> &str {
+        match self {
+            Event::Shutdown => "shutdown",
+            Event::StartFile { .. } => "start-file",
+            Event::EndFile { .. } => "end-file",
+            Event::FileLoaded => "file-loaded",
+            Event::TracksChanged => "tracks-changed",
+            Event::TrackSwitched => "track-switched",
+            Event::Idle => "idle",
+            Event::Pause => "pause",
+            Event::Unpause => "unpause",
+            Event::Tick => "tick",
+            Event::VideoReconfig => "video-reconfig",
+            Event::AudioReconfig => "audio-reconfig",
+            Event::MetadataUpdate => "metadata-update",
+            Event::Seek => "seek",
+            Event::PlaybackRestart => "playback-restart",
+            Event::PropertyChange { .. } => "property-change",
+            Event::ChapterChange => "chapter-change",
+            Event::Disconnected => "disconnected",
+            Event::Reconnected => "reconnected",
+            Event::LogMessage { .. } => "log-message",
+            Event::ClientMessage { .. } => "client-message",
+            Event::Hook { .. } => "hook",
+            Event::GetPropertyReply { .. } => "get-property-reply",
+            Event::SetPropertyReply { .. } => "set-property-reply",
+            Event::CommandReply { .. } => "command-reply",
+            Event::Other { ref name, .. } => name,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Property {
     Path(Option<String>),
@@ -65,10 +171,105 @@ pub enum Property {
     PlaybackTime(Option<f64>),
     Duration(Option<f64>),
     Metadata(Option<HashMap<String, MpvDataType>>),
-    Unknown { name: String, data: MpvDataType },
+    Volume(f64),
+    Mute(bool),
+    Speed(f64),
+    /// `None` if no playlist entry is selected.
+    PlaylistPos(Option<usize>),
+    PlaylistCount(usize),
+    Playlist(Vec<PlaylistEntry>),
+    TimePos(Option<f64>),
+    PercentPos(Option<f64>),
+    TimeRemaining(Option<f64>),
+    /// `None` before the first chapter or if the file has no chapters.
+    Chapter(Option<usize>),
+    TrackList(Vec<Track>),
+    /// The selected audio track, `None` if audio is disabled.
+    Aid(Option<usize>),
+    /// The selected subtitle track, `None` if subtitles are disabled.
+    Sid(Option<usize>),
+    /// The selected video track, `None` if video is disabled.
+    Vid(Option<usize>),
+    LoopFile(LoopMode),
+    LoopPlaylist(LoopMode),
+    IdleActive(bool),
+    EofReached(bool),
+    CoreIdle(bool),
+    PausedForCache(bool),
+    DemuxerCacheState(Option<HashMap<String, MpvDataType>>),
+    MediaTitle(Option<String>),
+    Filename(Option<String>),
+    Seeking(bool),
+    Unknown {
+        name: String,
+        data: MpvDataType,
+    },
+}
+
+/// The value of the `loop-file` and `loop-playlist` properties.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LoopMode {
+    No,
+    Inf,
+    /// Like [LoopMode::Inf], but also loops playlists whose entries all failed (`loop-playlist` only).
+    Force,
+    Times(usize),
+}
+
+impl Property {
+    /// The name of the mpv property this value belongs to.
+    pub fn name(&self) -> &str {
+        match self {
+            Property::Path(_) => "path",
+            Property::Pause(_) => "pause",
+            Property::PlaybackTime(_) => "playback-time",
+            Property::Duration(_) => "duration",
+            Property::Metadata(_) => "metadata",
+            Property::Volume(_) => "volume",
+            Property::Mute(_) => "mute",
+            Property::Speed(_) => "speed",
+            Property::PlaylistPos(_) => "playlist-pos",
+            Property::PlaylistCount(_) => "playlist-count",
+            Property::Playlist(_) => "playlist",
+            Property::TimePos(_) => "time-pos",
+            Property::PercentPos(_) => "percent-pos",
+            Property::TimeRemaining(_) => "time-remaining",
+            Property::Chapter(_) => "chapter",
+            Property::TrackList(_) => "track-list",
+            Property::Aid(_) => "aid",
+            Property::Sid(_) => "sid",
+            Property::Vid(_) => "vid",
+            Property::LoopFile(_) => "loop-file",
+            Property::LoopPlaylist(_) => "loop-playlist",
+            Property::IdleActive(_) => "idle-active",
+            Property::EofReached(_) => "eof-reached",
+            Property::CoreIdle(_) => "core-idle",
+            Property::PausedForCache(_) => "paused-for-cache",
+            Property::DemuxerCacheState(_) => "demuxer-cache-state",
+            Property::MediaTitle(_) => "media-title",
+            Property::Filename(_) => "filename",
+            Property::Seeking(_) => "seeking",
+            Property::Unknown { ref name, .. } => name,
+        }
+    }
 }
 
 pub enum MpvCommand {
+    AudioAdd {
+        file: String,
+        option: TrackAddOptions,
+        title: Option<String>,
+        lang: Option<String>,
+    },
+    /// Removes the given audio track, or the selected one if `None`.
+    AudioRemove(Option<usize>),
+    /// Reloads the given external audio track, or the selected one if `None`.
+    AudioReload(Option<usize>),
+    /// Steps back one video frame and pauses, which is slow as it has to decode from the last
+    /// keyframe.
+    FrameBackStep,
+    /// Steps forward one video frame and pauses.
+    FrameStep,
     LoadFile {
         file: String,
         option: PlaylistAddOptions,
@@ -87,24 +288,53 @@ pub enum MpvCommand {
     PlaylistRemove(usize),
     PlaylistShuffle,
     Quit,
+    RescanExternalFiles(RescanOptions),
+    RevertSeek(RevertSeekOptions),
     Seek {
         seconds: f64,
         option: SeekOptions,
+        precision: SeekPrecision,
     },
     Stop,
+    SubAdd {
+        file: String,
+        option: TrackAddOptions,
+        title: Option<String>,
+        lang: Option<String>,
+    },
+    /// Removes the given subtitle track, or the selected one if `None`.
+    SubRemove(Option<usize>),
+    /// Reloads the given external subtitle track, or the selected one if `None`.
+    SubReload(Option<usize>),
+    /// Seeks to the subtitle line _<n>_ lines away from the current one, e.g. `-1` for the
+    /// previous line.
+    SubSeek(isize),
+    VideoAdd {
+        file: String,
+        option: TrackAddOptions,
+        title: Option<String>,
+        lang: Option<String>,
+    },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A value as mpv sends it.
+///
+/// Deserializing picks the first variant that fits, so integers become [MpvDataType::Usize]
+/// or, if negative, [MpvDataType::Int64], and only other numbers [MpvDataType::Double].
+/// A [MpvDataType::Playlist] is serialized as a plain array and comes back as [MpvDataType::Array].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum MpvDataType {
-    Array(Vec<MpvDataType>),
+    Null,
     Bool(bool),
+    Usize(usize),
+    /// A negative integer, non-negative ones are [MpvDataType::Usize].
+    Int64(i64),
     Double(f64),
-    HashMap(HashMap<String, MpvDataType>),
-    Null,
-    Playlist(Playlist),
     String(String),
-    Usize(usize),
+    Array(Vec<MpvDataType>),
+    Playlist(Playlist),
+    HashMap(HashMap<String, MpvDataType>),
 }
 
 pub enum NumberChangeOptions {
@@ -123,6 +353,22 @@ pub enum PlaylistAddTypeOptions {
     Playlist,
 }
 
+pub enum RescanOptions {
+    /// Select the default tracks again after the rescan.
+    Reselect,
+    /// Keep the current track selection.
+    KeepSelection,
+}
+
+pub enum RevertSeekOptions {
+    /// Go back to the position before the last seek, or to the marked position.
+    Revert,
+    /// Mark the current position, the next revert goes there.
+    Mark,
+    /// Like [RevertSeekOptions::Mark], but the mark survives further reverts.
+    MarkPermanent,
+}
+
 pub enum SeekOptions {
     Relative,
     Absolute,
@@ -130,6 +376,208 @@ pub enum SeekOptions {
     AbsolutePercent,
 }
 
+/// How exactly mpv seeks. The default follows mpv's `hr-seek` option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SeekPrecision {
+    #[default]
+    Default,
+    /// Seek to the nearest keyframe, which is fast but not exact.
+    Keyframes,
+    /// Decode up to the exact position.
+    Exact,
+}
+
+/// What mpv does with an external track after adding it.
+pub enum TrackAddOptions {
+    /// Select the new track.
+    Select,
+    /// Add the track without selecting it, unless mpv's track selection picks it.
+    Auto,
+    /// Reuse an already added track with the same file name instead of adding it twice.
+    Cached,
+}
+
+impl MpvCommand {
+    /// Splits the command into the name mpv knows it by and its arguments.
+    fn into_args(self) -> (&'static str, Vec<Value>) {
+        match self {
+            MpvCommand::AudioAdd {
+                file,
+                option,
+                title,
+                lang,
+            } => ("audio-add", track_add_args(file, option, title, lang)),
+            MpvCommand::AudioRemove(id) => ("audio-remove", optional_id_args(id)),
+            MpvCommand::AudioReload(id) => ("audio-reload", optional_id_args(id)),
+            MpvCommand::FrameBackStep => ("frame-back-step", vec![]),
+            MpvCommand::FrameStep => ("frame-step", vec![]),
+            MpvCommand::LoadFile { file, option } => {
+                ("loadfile", vec![json!(file), json!(option.as_str())])
+            }
+            MpvCommand::LoadList { file, option } => {
+                ("loadlist", vec![json!(file), json!(option.as_str())])
+            }
+            MpvCommand::PlaylistClear => ("playlist-clear", vec![]),
+            MpvCommand::PlaylistMove { from, to } => {
+                ("playlist-move", vec![json!(from), json!(to)])
+            }
+            MpvCommand::PlaylistNext => ("playlist-next", vec![]),
+            MpvCommand::PlaylistPrev => ("playlist-prev", vec![]),
+            MpvCommand::PlaylistRemove(id) => ("playlist-remove", vec![json!(id)]),
+            MpvCommand::PlaylistShuffle => ("playlist-shuffle", vec![]),
+            MpvCommand::Quit => ("quit", vec![]),
+            MpvCommand::RescanExternalFiles(option) => {
+                ("rescan-external-files", vec![json!(option.as_str())])
+            }
+            MpvCommand::RevertSeek(option) => ("revert-seek", option.as_args()),
+            MpvCommand::Seek {
+                seconds,
+                option,
+                precision,
+            } => {
+                let flags = match precision.as_str() {
+                    Some(precision) => format!("{}+{}", option.as_str(), precision),
+                    None => option.as_str().to_string(),
+                };
+                ("seek", vec![json!(seconds), json!(flags)])
+            }
+            MpvCommand::Stop => ("stop", vec![]),
+            MpvCommand::SubAdd {
+                file,
+                option,
+                title,
+                lang,
+            } => ("sub-add", track_add_args(file, option, title, lang)),
+            MpvCommand::SubRemove(id) => ("sub-remove", optional_id_args(id)),
+            MpvCommand::SubReload(id) => ("sub-reload", optional_id_args(id)),
+            MpvCommand::SubSeek(lines) => ("sub-seek", vec![json!(lines)]),
+            MpvCommand::VideoAdd {
+                file,
+                option,
+                title,
+                lang,
+            } => ("video-add", track_add_args(file, option, title, lang)),
+        }
+    }
+}
+
+/// Arguments are positional, so a language without a title needs an empty title in front of it.
+fn track_add_args(
+    file: String,
+    option: TrackAddOptions,
+    title: Option<String>,
+    lang: Option<String>,
+) -> Vec<Value> {
+    let mut args = vec![json!(file), json!(option.as_str())];
+    if title.is_some() || lang.is_some() {
+        args.push(json!(title.unwrap_or_default()));
+    }
+    if let Some(lang) = lang {
+        args.push(json!(lang));
+    }
+    args
+}
+
+fn optional_id_args(id: Option<usize>) -> Vec<Value> {
+    id.into_iter().map(|id| json!(id)).collect()
+}
+
+/// Parses a timestamp like `"01:02:03.5"`, `"2:03"` or `"123.5"` into seconds.
+///
+/// Minutes and seconds after the leading field must be below 60.
+/// Fails with [ErrorCode::InvalidTimestamp] for anything else, including negative values.
+///
+/// # Example
+/// ```
+/// assert_eq!(mpvipc::parse_timestamp("01:02:03.5").unwrap(), 3723.5);
+/// assert!(mpvipc::parse_timestamp("1:60").is_err());
+/// ```
+pub fn parse_timestamp(timestamp: &str) -> Result<f64, Error> {
+    let invalid = || Error(ErrorCode::InvalidTimestamp(timestamp.to_string()));
+    let fields: Vec<&str> = timestamp.trim().split(':').collect();
+    if fields.len() > 3 {
+        return Err(invalid());
+    }
+    let mut seconds = 0f64;
+    for (index, field) in fields.iter().enumerate() {
+        let (whole, fraction) = match field.split_once('.') {
+            Some((whole, fraction)) if index == fields.len() - 1 => (whole, fraction),
+            None => (*field, ""),
+            Some(_) => return Err(invalid()),
+        };
+        let digits = whole.bytes().chain(fraction.bytes());
+        if whole.is_empty() || !digits.into_iter().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let value: f64 = field.parse().map_err(|_| invalid())?;
+        // Only the leading field may go past 59, e.g. "90:00" for an hour and a half.
+        if index > 0 && value >= 60.0 {
+            return Err(invalid());
+        }
+        seconds = seconds * 60.0 + value;
+    }
+    Ok(seconds)
+}
+
+impl PlaylistAddOptions {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PlaylistAddOptions::Append => "append",
+            PlaylistAddOptions::Replace => "replace",
+        }
+    }
+}
+
+impl RescanOptions {
+    fn as_str(&self) -> &'static str {
+        match self {
+            RescanOptions::Reselect => "reselect",
+            RescanOptions::KeepSelection => "keep-selection",
+        }
+    }
+}
+
+impl RevertSeekOptions {
+    fn as_args(&self) -> Vec<Value> {
+        match self {
+            RevertSeekOptions::Revert => vec![],
+            RevertSeekOptions::Mark => vec![json!("mark")],
+            RevertSeekOptions::MarkPermanent => vec![json!("mark-permanent")],
+        }
+    }
+}
+
+impl SeekOptions {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SeekOptions::Absolute => "absolute",
+            SeekOptions::Relative => "relative",
+            SeekOptions::AbsolutePercent => "absolute-percent",
+            SeekOptions::RelativePercent => "relative-percent",
+        }
+    }
+}
+
+impl SeekPrecision {
+    fn as_str(&self) -> Option<&'static str> {
+        match self {
+            SeekPrecision::Default => None,
+            SeekPrecision::Keyframes => Some("keyframes"),
+            SeekPrecision::Exact => Some("exact"),
+        }
+    }
+}
+
+impl TrackAddOptions {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TrackAddOptions::Select => "select",
+            TrackAddOptions::Auto => "auto",
+            TrackAddOptions::Cached => "cached",
+        }
+    }
+}
+
 pub enum Switch {
     On,
     Off,
@@ -138,39 +586,457 @@ pub enum Switch {
 
 #[derive(Debug, Clone)]
 pub enum ErrorCode {
-    MpvError(String),
+    /// mpv rejected the request. _context_ names the command or property involved, if known.
+    MpvError {
+        kind: MpvErrorKind,
+        context: Option<String>,
+    },
     JsonParseError(String),
     ConnectError(String),
+    Timeout,
+    /// A [Subscription] fell behind and missed this many events.
+    Lagged(u64),
+    /// The string could not be parsed by [parse_timestamp].
+    InvalidTimestamp(String),
     JsonContainsUnexptectedType,
     UnexpectedResult,
     UnexpectedValue,
     UnsupportedType,
     ValueDoesNotContainBool,
+    ValueDoesNotContainF32,
     ValueDoesNotContainF64,
     ValueDoesNotContainHashMap,
+    ValueDoesNotContainI32,
+    ValueDoesNotContainI64,
     ValueDoesNotContainPlaylist,
     ValueDoesNotContainString,
+    ValueDoesNotContainU32,
+    ValueDoesNotContainU64,
     ValueDoesNotContainUsize,
 }
 
+/// The errors mpv reports in the `error` field of a reply.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MpvErrorKind {
+    PropertyNotFound,
+    PropertyUnavailable,
+    InvalidParameter,
+    CommandError,
+    /// The operation, or the format a value was passed in, is not supported.
+    Unsupported,
+    LoadingFailed,
+    /// Any other error, with mpv's message.
+    Raw(String),
+}
+
+/// Replies that are still awaited, keyed by the `request_id` their command was sent with.
+type PendingRequests = Mutex<HashMap<u32, oneshot::Sender<Data>>>;
+
+/// The state of the socket connection, shared between [Mpv] and its listener task.
+struct Connection {
+    /// `None` while the connection is lost.
+    writer: tokio::sync::Mutex<Option<OwnedWriteHalf>>,
+    pending_requests: PendingRequests,
+    next_request_id: AtomicU32,
+    in_flight_requests: Semaphore,
+    command_timeout: Option<Duration>,
+    /// Properties registered through [Mpv::observe_property] and [Mpv::watch],
+    /// registered again after a reconnect.
+    observed_properties: Mutex<HashMap<isize, Observer>>,
+    /// Fans events out to every [Subscription]. Dropped once the listener has stopped for good,
+    /// which ends every subscription.
+    subscribers: Mutex<Option<broadcast::Sender<Event>>>,
+    /// Events waiting to be handed to [Mpv::event_receiver].
+    events: EventQueue,
+    /// Latest values of the properties observed through [Mpv::watch], keyed by observer id.
+    watchers: Mutex<HashMap<isize, watch::Sender<Option<Value>>>>,
+    /// Ids for [Mpv::watch] count down from `isize::MAX`, away from the ids picked by hand.
+    next_watch_id: AtomicIsize,
+}
+
+/// Decouples the listener from [Mpv::event_receiver]: pushing never waits, so a consumer
+/// that stops reading can't hold up command replies. Once `capacity` events are queued,
+/// the [OverflowPolicy] decides which event is given up.
+struct EventQueue {
+    events: Mutex<VecDeque<Event>>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    ready: Notify,
+    /// Set once the receiver was dropped, after which events are discarded right away.
+    closed: AtomicBool,
+    /// Set once no more events will be pushed, so [EventQueue::pop] ends after the last one.
+    finished: AtomicBool,
+}
+
+impl EventQueue {
+    fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+        EventQueue {
+            events: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+            policy,
+            ready: Notify::new(),
+            closed: AtomicBool::new(false),
+            finished: AtomicBool::new(false),
+        }
+    }
+
+    fn push(&self, event: Event) {
+        if self.closed.load(Ordering::Relaxed) {
+            return;
+        }
+        let mut events = self.events.lock().unwrap();
+        if events.len() >= self.capacity {
+            match self.policy {
+                OverflowPolicy::DropOldest => {
+                    if let Some(dropped) = events.pop_front() {
+                        debug!("Event queue is full, dropping {}", dropped.name());
+                    }
+                }
+                OverflowPolicy::DropNewest => {
+                    debug!("Event queue is full, dropping {}", event.name());
+                    return;
+                }
+                OverflowPolicy::CoalesceProperties => {
+                    if let Event::PropertyChange { id, .. } = event {
+                        let queued = events.iter_mut().rev().find(|queued| match queued {
+                            Event::PropertyChange { id: queued_id, .. } => *queued_id == id,
+                            _ => false,
+                        });
+                        if let Some(queued) = queued {
+                            *queued = event;
+                            return;
+                        }
+                    }
+                    if let Some(dropped) = events.pop_front() {
+                        debug!("Event queue is full, dropping {}", dropped.name());
+                    }
+                }
+            }
+        }
+        events.push_back(event);
+        drop(events);
+        self.ready.notify_one();
+    }
+
+    /// Waits for the next event, `None` once the queue is finished and empty.
+    async fn pop(&self) -> Option<Event> {
+        loop {
+            if let Some(event) = self.events.lock().unwrap().pop_front() {
+                return Some(event);
+            }
+            if self.finished.load(Ordering::Acquire) {
+                return None;
+            }
+            self.ready.notified().await;
+        }
+    }
+
+    fn finish(&self) {
+        self.finished.store(true, Ordering::Release);
+        // Stores a permit if the forwarder isn't waiting yet, so it can't miss the wakeup.
+        self.ready.notify_one();
+    }
+
+    fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+        self.events.lock().unwrap().clear();
+    }
+}
+
 pub struct Mpv {
-    stream: UnixStream,
-    reader: BufReader<UnixStream>,
+    connection: Arc<Connection>,
+    listener: JoinHandle<()>,
+    forwarder: JoinHandle<()>,
     name: String,
+    /// Every event mpv sends, see [ConnectOptions::overflow_policy]. Yields `None` once the
+    /// connection is closed for good and every remaining event was received.
     pub event_receiver: Option<Receiver<Event>>,
-    response_receiver: Mutex<Receiver<Data>>,
 }
-#[derive(Debug, Clone, Serialize, Deserialize)]
+
+/// Settings used when connecting to mpv, created with [Mpv::builder] or [ConnectOptions::new].
+///
+/// # Example
+/// ```no_run
+/// use mpvipc::{Mpv, Error};
+/// use std::time::Duration;
+/// #[tokio::main]
+/// async fn main() -> Result<(), Error> {
+///     let mpv = Mpv::builder()
+///         .command_timeout(Duration::from_secs(2))
+///         .event_capacity(64)
+///         .connect("/tmp/mpvsocket")
+///         .await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConnectOptions {
+    command_timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    event_capacity: usize,
+    overflow_policy: OverflowPolicy,
+    response_capacity: usize,
+    reconnect: Option<ReconnectPolicy>,
+}
+
+/// What happens to new events once [ConnectOptions::event_capacity] events are waiting
+/// in [Mpv::event_receiver], see [ConnectOptions::overflow_policy].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Drop the oldest queued event to make room for the new one.
+    DropOldest,
+    /// Keep the queued events and drop the new one.
+    DropNewest,
+    /// Replace the last queued [Event::PropertyChange] with the same id by the new value,
+    /// so only the latest value of each observed property is kept.
+    /// Other events fall back to [OverflowPolicy::DropOldest].
+    CoalesceProperties,
+}
+
+/// How the connection is retried after mpv went away, see [ConnectOptions::reconnect].
+///
+/// The delay between two attempts starts at `initial_delay` and doubles after every
+/// failed attempt, up to `max_delay`.
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    /// Give up after this many failed attempts. `None` retries forever.
+    pub max_attempts: Option<usize>,
+}
+/// A command started with `"async": true`, see [Mpv::run_command_async].
+///
+/// mpv keeps processing other requests while the command runs. Awaiting the handle
+/// resolves once mpv reports that the command has finished; dropping it discards the result.
+pub struct AsyncCommand {
+    command: String,
+    receiver: oneshot::Receiver<Data>,
+    _guard: PendingRequestGuard,
+}
+
+/// A chapter from the `chapter-list` property, see [Mpv::get_chapters].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Chapter {
+    pub title: Option<String>,
+    /// Start of the chapter in seconds.
+    pub time: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Playlist(pub Vec<PlaylistEntry>);
+
+/// An audio, video or subtitle track from the `track-list` property, see [Mpv::get_tracks].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Track {
+    /// The id to select the track with, unique per [TrackType].
+    pub id: usize,
+    #[serde(rename = "type")]
+    pub track_type: TrackType,
+    pub title: Option<String>,
+    pub lang: Option<String>,
+    pub codec: Option<String>,
+    #[serde(default)]
+    pub default: bool,
+    #[serde(default)]
+    pub forced: bool,
+    #[serde(default)]
+    pub selected: bool,
+    /// Loaded from a separate file, e.g. a subtitle file added with `sub-add`.
+    #[serde(default)]
+    pub external: bool,
+    pub external_filename: Option<String>,
+    /// Video width in pixels.
+    pub demux_w: Option<usize>,
+    /// Video height in pixels.
+    pub demux_h: Option<usize>,
+    pub demux_channel_count: Option<usize>,
+    /// The audio channel layout, e.g. `"5.1"`.
+    pub demux_channels: Option<String>,
+    pub demux_samplerate: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrackType {
+    Audio,
+    Video,
+    Sub,
+}
 #[derive(Debug, Clone)]
 pub struct Error(pub ErrorCode);
 
+/// An independent stream of events created with [Mpv::subscribe].
+///
+/// Every subscription receives its own copy of each event. A subscriber that falls more than
+/// [ConnectOptions::event_capacity] events behind misses the oldest ones and is told so by
+/// [Subscription::recv] returning [ErrorCode::Lagged]; other subscribers are not affected.
+pub struct Subscription {
+    receiver: broadcast::Receiver<Event>,
+    kinds: Vec<String>,
+    properties: Vec<String>,
+}
+
+impl Subscription {
+    /// Only receive events named _<name>_ (see [Event::name]), e.g. `"end-file"`.
+    /// May be combined with further [Subscription::kind] and [Subscription::property] filters.
+    pub fn kind(mut self, name: &str) -> Self {
+        self.kinds.push(name.to_string());
+        self
+    }
+
+    /// Only receive [Event::PropertyChange] events for _<property>_, e.g. `"volume"`.
+    /// The property still has to be observed with [Mpv::observe_property].
+    pub fn property(mut self, property: &str) -> Self {
+        self.properties.push(property.to_string());
+        self
+    }
+
+    /// Waits for the next event that passes the filters.
+    ///
+    /// Returns [ErrorCode::Lagged] once if events were missed, after which the following
+    /// events are received again. Fails with [ErrorCode::ConnectError] once the connection
+    /// to mpv is closed for good and every remaining event was received.
+    pub async fn recv(&mut self) -> Result<Event, Error> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(event) if self.matches(&event) => return Ok(event),
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    return Err(Error(ErrorCode::Lagged(missed)))
+                }
+                Err(broadcast::error::RecvError::Closed) => {
+                    return Err(Error(ErrorCode::ConnectError(
+                        "The event listener has stopped".to_string(),
+                    )))
+                }
+            }
+        }
+    }
+
+    fn matches(&self, event: &Event) -> bool {
+        if self.kinds.is_empty() && self.properties.is_empty() {
+            return true;
+        }
+        if self.kinds.iter().any(|kind| kind == event.name()) {
+            return true;
+        }
+        match event {
+            Event::PropertyChange { property, .. } => {
+                self.properties.iter().any(|name| name == property.name())
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The values of a property observed with [Mpv::watch].
+///
+/// Yields the current value first and then every change. A consumer that falls behind
+/// skips straight to the latest value. Dropping the stream stops observing the property;
+/// the stream ends once the [Mpv] instance is dropped.
+pub struct PropertyStream<T> {
+    values: WatchStream<Option<Value>>,
+    connection: Weak<Connection>,
+    id: isize,
+    property: String,
+    _type: PhantomData<fn() -> T>,
+}
+
+/// A property observation registered with mpv, see [Mpv::observers].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Observer {
+    pub id: isize,
+    pub property: String,
+    /// The type values are decoded as, for observations created by [Mpv::watch].
+    pub value_type: Option<&'static str>,
+}
+
+impl<T> PropertyStream<T> {
+    /// The observer id that was allocated for this stream.
+    pub fn id(&self) -> isize {
+        self.id
+    }
+}
+
+impl<T: TypeHandler> Stream for PropertyStream<T> {
+    type Item = Result<T, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match Pin::new(&mut self.values).poll_next(cx) {
+                // Nothing was received yet.
+                Poll::Ready(Some(None)) => continue,
+                Poll::Ready(Some(Some(data))) => {
+                    let value = json!({ "error": "success", "data": data });
+                    return Poll::Ready(Some(T::get_value(value)));
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<T> Drop for PropertyStream<T> {
+    fn drop(&mut self) {
+        let connection = match self.connection.upgrade() {
+            Some(connection) => connection,
+            None => return,
+        };
+        connection.watchers.lock().unwrap().remove(&self.id);
+        let observed = connection
+            .observed_properties
+            .lock()
+            .unwrap()
+            .remove(&self.id);
+        if observed.is_none() {
+            // Already stopped through Mpv::unobserve_property.
+            return;
+        }
+        let (id, property) = (self.id, self.property.clone());
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                if let Err(why) = unobserve_property_on(&connection, &id).await {
+                    warn!("Could not stop observing property {}: {}", property, why);
+                }
+            });
+        }
+    }
+}
+
 impl Drop for Mpv {
     fn drop(&mut self) {
         self.disconnect();
     }
 }
 
+impl AsyncCommand {
+    /// Waits for the command to finish and decodes the data mpv returned as `T`.
+    pub async fn result<T: TypeHandler>(self) -> Result<T, Error> {
+        match self.receiver.await {
+            Ok(data) => {
+                check_response(&data, "command", &self.command)?;
+                T::get_value(json!({ "error": data.error, "data": data.data }))
+            }
+            Err(_) => Err(connection_closed()),
+        }
+    }
+}
+
+impl Future for AsyncCommand {
+    type Output = Result<(), Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.receiver)
+            .poll(cx)
+            .map(|response| match response {
+                Ok(data) => check_response(&data, "command", &self.command),
+                Err(_) => Err(connection_closed()),
+            })
+    }
+}
+
 impl fmt::Debug for Mpv {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt.debug_tuple("Mpv").field(&self.name).finish()
@@ -190,12 +1056,21 @@ struct Response {
     error: String,
 }
 
-impl Into<Data> for Response {
-    fn into(self) -> Data {
+/// A command as it is sent over the socket.
+#[derive(Serialize, Debug)]
+struct Request<'a> {
+    command: &'a Value,
+    request_id: u32,
+    #[serde(rename = "async", skip_serializing_if = "std::ops::Not::not")]
+    is_async: bool,
+}
+
+impl From<Response> for Data {
+    fn from(response: Response) -> Self {
         Data {
             data: serde_json::Value::Null,
-            request_id: self.request_id,
-            error: self.error,
+            request_id: response.request_id,
+            error: response.error,
         }
     }
 }
@@ -203,6 +1078,9 @@ impl Into<Data> for Response {
 #[derive(Deserialize, Serialize, Debug, Clone)]
 struct MpvEvent {
     event: String,
+    /// Every other field of the event.
+    #[serde(flatten)]
+    data: serde_json::Map<String, Value>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -214,28 +1092,50 @@ enum MpvMessage {
     Other(serde_json::Value),
 }
 
-//?  Can't really be implemented due to listeners. Would have to start a new loop with new senders and receivers.
-// impl Clone for Mpv {
-//     fn clone(&self) -> Self {
-//         let stream = self.stream.try_clone().expect("cloning UnixStream");
-//         let cloned_stream = stream.try_clone().expect("cloning UnixStream");
-//         Mpv {
-//             stream,
-//             reader: BufReader::new(cloned_stream),
-//             name: self.name.clone(),
-//         }
-//     }
-
-//     fn clone_from(&mut self, source: &Self) {
-//         let stream = source.stream.try_clone().expect("cloning UnixStream");
-//         let cloned_stream = stream.try_clone().expect("cloning UnixStream");
-//         *self = Mpv {
-//             stream,
-//             reader: BufReader::new(cloned_stream),
-//             name: source.name.clone(),
-//         }
-//     }
-// }
+/// Hands the new value of a property to the [PropertyStream] observing it, if any.
+fn notify_watcher(connection: &Connection, event: &MpvEvent) {
+    if event.event != "property-change" {
+        return;
+    }
+    let id = match event.data.get("id").and_then(Value::as_i64) {
+        Some(id) => id as isize,
+        None => return,
+    };
+    if let Some(watcher) = connection.watchers.lock().unwrap().get(&id) {
+        let data = event.data.get("data").cloned().unwrap_or(Value::Null);
+        // Sending only fails if the stream is being dropped.
+        let _ = watcher.send(Some(data));
+    }
+}
+
+fn send_event(connection: &Connection, event: Event) {
+    if let Some(subscribers) = connection.subscribers.lock().unwrap().as_ref() {
+        // Sending only fails if nobody is subscribed.
+        let _ = subscribers.send(event.clone());
+    }
+    connection.events.push(event);
+}
+
+/// Lets event consumers know that no more events will arrive, once the listener has stopped
+/// for good.
+fn close_events(connection: &Connection) {
+    connection.subscribers.lock().unwrap().take();
+    connection.events.finish();
+}
+
+/// Hands a reply to the command that is waiting for it.
+/// Replies nobody is waiting for anymore (e.g. because the caller was dropped) are discarded.
+fn dispatch_response(pending: &PendingRequests, response: Data) {
+    let waiter = pending.lock().unwrap().remove(&response.request_id);
+    match waiter {
+        Some(sender) => {
+            if sender.send(response).is_err() {
+                debug!("Caller stopped waiting for the reply, discarding it");
+            }
+        }
+        None => warn!("Unclaimed reply: {:#?}", response),
+    }
+}
 
 impl Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -245,12 +1145,82 @@ impl Display for Error {
 
 impl std::error::Error for Error {}
 
+impl Error {
+    /// The kind of error mpv reported, or `None` if the error didn't come from mpv.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use mpvipc::{Mpv, MpvErrorKind, Error};
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let mpv = Mpv::connect("/tmp/mpvsocket").await?;
+    ///     match mpv.get_property::<f64>("duration").await {
+    ///         Err(e) if e.kind() == Some(&MpvErrorKind::PropertyUnavailable) => {}
+    ///         result => println!("{:?}", result?),
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn kind(&self) -> Option<&MpvErrorKind> {
+        match self.0 {
+            ErrorCode::MpvError { ref kind, .. } => Some(kind),
+            _ => None,
+        }
+    }
+}
+
+impl MpvErrorKind {
+    /// Maps one of mpv's error messages (see `mpv_error_string` in mpv's `client.c`) to its kind.
+    pub fn from_mpv_error(error: &str) -> MpvErrorKind {
+        match error {
+            "property not found" => MpvErrorKind::PropertyNotFound,
+            "property unavailable" => MpvErrorKind::PropertyUnavailable,
+            "invalid parameter" => MpvErrorKind::InvalidParameter,
+            "error running command" => MpvErrorKind::CommandError,
+            "unsupported format for accessing property"
+            | "unsupported format for accessing option"
+            | "not supported"
+            | "operation not implemented" => MpvErrorKind::Unsupported,
+            "loading failed" => MpvErrorKind::LoadingFailed,
+            _ => MpvErrorKind::Raw(error.to_string()),
+        }
+    }
+}
+
+impl Display for MpvErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MpvErrorKind::PropertyNotFound => f.write_str("property not found"),
+            MpvErrorKind::PropertyUnavailable => f.write_str("property unavailable"),
+            MpvErrorKind::InvalidParameter => f.write_str("invalid parameter"),
+            MpvErrorKind::CommandError => f.write_str("error running command"),
+            MpvErrorKind::Unsupported => f.write_str("unsupported"),
+            MpvErrorKind::LoadingFailed => f.write_str("loading failed"),
+            MpvErrorKind::Raw(ref msg) => f.write_str(msg),
+        }
+    }
+}
+
 impl Display for ErrorCode {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             ErrorCode::ConnectError(ref msg) => f.write_str(&format!("ConnectError: {}", msg)),
             ErrorCode::JsonParseError(ref msg) => f.write_str(&format!("JsonParseError: {}", msg)),
-            ErrorCode::MpvError(ref msg) => f.write_str(&format!("MpvError: {}", msg)),
+            ErrorCode::MpvError {
+                ref kind,
+                context: Some(ref context),
+            } => f.write_str(&format!("MpvError: {} ({})", kind, context)),
+            ErrorCode::MpvError {
+                ref kind,
+                context: None,
+            } => f.write_str(&format!("MpvError: {}", kind)),
+            ErrorCode::Timeout => f.write_str("Timed out waiting for mpv"),
+            ErrorCode::Lagged(missed) => {
+                f.write_str(&format!("Subscriber missed {} events", missed))
+            }
+            ErrorCode::InvalidTimestamp(ref timestamp) => {
+                f.write_str(&format!("Invalid timestamp: {}", timestamp))
+            }
             ErrorCode::JsonContainsUnexptectedType => {
                 f.write_str("Mpv sent a value with an unexpected type")
             }
@@ -260,18 +1230,33 @@ impl Display for ErrorCode {
             ErrorCode::ValueDoesNotContainBool => {
                 f.write_str("The received value is not of type \'std::bool\'")
             }
+            ErrorCode::ValueDoesNotContainF32 => {
+                f.write_str("The received value is not of type \'std::f32\'")
+            }
             ErrorCode::ValueDoesNotContainF64 => {
                 f.write_str("The received value is not of type \'std::f64\'")
             }
             ErrorCode::ValueDoesNotContainHashMap => {
                 f.write_str("The received value is not of type \'std::collections::HashMap\'")
             }
+            ErrorCode::ValueDoesNotContainI32 => {
+                f.write_str("The received value is not of type \'std::i32\'")
+            }
+            ErrorCode::ValueDoesNotContainI64 => {
+                f.write_str("The received value is not of type \'std::i64\'")
+            }
             ErrorCode::ValueDoesNotContainPlaylist => {
                 f.write_str("The received value is not of type \'mpvipc::Playlist\'")
             }
             ErrorCode::ValueDoesNotContainString => {
                 f.write_str("The received value is not of type \'std::string::String\'")
             }
+            ErrorCode::ValueDoesNotContainU32 => {
+                f.write_str("The received value is not of type \'std::u32\'")
+            }
+            ErrorCode::ValueDoesNotContainU64 => {
+                f.write_str("The received value is not of type \'std::u64\'")
+            }
             ErrorCode::ValueDoesNotContainUsize => {
                 f.write_str("The received value is not of type \'std::usize\'")
             }
@@ -279,11 +1264,152 @@ impl Display for ErrorCode {
     }
 }
 
+impl Default for ConnectOptions {
+    fn default() -> Self {
+        ConnectOptions {
+            command_timeout: None,
+            connect_timeout: None,
+            event_capacity: 8,
+            overflow_policy: OverflowPolicy::DropOldest,
+            response_capacity: 8,
+            reconnect: None,
+        }
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        ReconnectPolicy {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    // `Option::is_none_or` would raise the minimum Rust version to 1.82.
+    #[allow(clippy::unnecessary_map_or)]
+    async fn reconnect(&self, socket: &str) -> Option<UnixStream> {
+        let mut delay = self.initial_delay;
+        let mut attempts = 0;
+        while self.max_attempts.map_or(true, |max| attempts < max) {
+            tokio::time::sleep(delay).await;
+            attempts += 1;
+            match UnixStream::connect(socket).await {
+                Ok(stream) => return Some(stream),
+                Err(why) => debug!("Reconnect attempt {} failed: {}", attempts, why),
+            }
+            delay = std::cmp::min(delay * 2, self.max_delay);
+        }
+        None
+    }
+}
+
+impl ConnectOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How long a command may wait for its reply before failing with [ErrorCode::Timeout].
+    /// Without it, commands wait for as long as the connection is open.
+    pub fn command_timeout(mut self, timeout: Duration) -> Self {
+        self.command_timeout = Some(timeout);
+        self
+    }
+
+    /// How long to wait for the socket connection to be established.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// The number of events buffered in [Mpv::event_receiver] and for each
+    /// [Subscription] (defaults to 8, at least 1).
+    pub fn event_capacity(mut self, capacity: usize) -> Self {
+        self.event_capacity = capacity.max(1);
+        self
+    }
+
+    /// Which event is dropped when [Mpv::event_receiver] is not read fast enough
+    /// (defaults to [OverflowPolicy::DropOldest]). Command replies are never held up by events.
+    pub fn overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.overflow_policy = policy;
+        self
+    }
+
+    /// The number of commands that may wait for a reply at the same time (defaults to 8,
+    /// at least 1). Further commands wait until one of them has been answered.
+    pub fn response_capacity(mut self, capacity: usize) -> Self {
+        self.response_capacity = capacity.max(1);
+        self
+    }
+
+    /// Keeps the [Mpv] instance usable after mpv was restarted or crashed: the socket is
+    /// retried according to _<policy>_ and every property registered with
+    /// [Mpv::observe_property] is observed again once the connection is back.
+    ///
+    /// [Event::Disconnected] and [Event::Reconnected] report the state of the connection.
+    /// Commands sent while disconnected fail with [ErrorCode::ConnectError].
+    pub fn reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = Some(policy);
+        self
+    }
+
+    pub async fn connect(&self, socket: &str) -> Result<Mpv, Error> {
+        let connection = UnixStream::connect(socket);
+        let connection = match self.connect_timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, connection).await {
+                Ok(connection) => connection,
+                Err(_) => return Err(Error(ErrorCode::Timeout)),
+            },
+            None => connection.await,
+        };
+        match connection {
+            Ok(stream) => {
+                let (reader, writer) = stream.into_split();
+                // Events are buffered in the EventQueue, the channel only hands them over.
+                let (eventtx, eventrx) = tokio::sync::mpsc::channel::<Event>(1);
+                let connection = Arc::new(Connection {
+                    writer: tokio::sync::Mutex::new(Some(writer)),
+                    pending_requests: PendingRequests::default(),
+                    next_request_id: AtomicU32::new(1),
+                    in_flight_requests: Semaphore::new(self.response_capacity),
+                    command_timeout: self.command_timeout,
+                    observed_properties: Mutex::default(),
+                    subscribers: Mutex::new(Some(broadcast::channel(self.event_capacity).0)),
+                    events: EventQueue::new(self.event_capacity, self.overflow_policy),
+                    watchers: Mutex::default(),
+                    next_watch_id: AtomicIsize::new(isize::MAX),
+                });
+
+                let forwarder = Mpv::start_forwarder(connection.clone(), eventtx);
+                let listener = Mpv::start_listener(
+                    connection.clone(),
+                    reader,
+                    socket.to_string(),
+                    self.reconnect.clone(),
+                );
+                Ok(Mpv {
+                    connection,
+                    listener,
+                    forwarder,
+                    name: String::from(socket),
+                    event_receiver: Some(eventrx),
+                })
+            }
+            Err(internal_error) => Err(Error(ErrorCode::ConnectError(internal_error.to_string()))),
+        }
+    }
+}
+
+#[deprecated(note = "Mpv::get_property accepts any serde type, use it instead")]
 #[async_trait]
 pub trait GetPropertyTypeHandler: Sized {
     async fn get_property_generic(instance: &Mpv, property: &str) -> Result<Self, Error>;
 }
 
+#[allow(deprecated)]
 #[async_trait]
 impl GetPropertyTypeHandler for bool {
     async fn get_property_generic(instance: &Mpv, property: &str) -> Result<bool, Error> {
@@ -291,6 +1417,7 @@ impl GetPropertyTypeHandler for bool {
     }
 }
 
+#[allow(deprecated)]
 #[async_trait]
 impl GetPropertyTypeHandler for String {
     async fn get_property_generic(instance: &Mpv, property: &str) -> Result<String, Error> {
@@ -298,6 +1425,7 @@ impl GetPropertyTypeHandler for String {
     }
 }
 
+#[allow(deprecated)]
 #[async_trait]
 impl GetPropertyTypeHandler for f64 {
     async fn get_property_generic(instance: &Mpv, property: &str) -> Result<f64, Error> {
@@ -305,6 +1433,7 @@ impl GetPropertyTypeHandler for f64 {
     }
 }
 
+#[allow(deprecated)]
 #[async_trait]
 impl GetPropertyTypeHandler for usize {
     async fn get_property_generic(instance: &Mpv, property: &str) -> Result<usize, Error> {
@@ -312,6 +1441,47 @@ impl GetPropertyTypeHandler for usize {
     }
 }
 
+#[allow(deprecated)]
+#[async_trait]
+impl GetPropertyTypeHandler for f32 {
+    async fn get_property_generic(instance: &Mpv, property: &str) -> Result<f32, Error> {
+        get_mpv_property::<f32>(instance, property).await
+    }
+}
+
+#[allow(deprecated)]
+#[async_trait]
+impl GetPropertyTypeHandler for i32 {
+    async fn get_property_generic(instance: &Mpv, property: &str) -> Result<i32, Error> {
+        get_mpv_property::<i32>(instance, property).await
+    }
+}
+
+#[allow(deprecated)]
+#[async_trait]
+impl GetPropertyTypeHandler for i64 {
+    async fn get_property_generic(instance: &Mpv, property: &str) -> Result<i64, Error> {
+        get_mpv_property::<i64>(instance, property).await
+    }
+}
+
+#[allow(deprecated)]
+#[async_trait]
+impl GetPropertyTypeHandler for u32 {
+    async fn get_property_generic(instance: &Mpv, property: &str) -> Result<u32, Error> {
+        get_mpv_property::<u32>(instance, property).await
+    }
+}
+
+#[allow(deprecated)]
+#[async_trait]
+impl GetPropertyTypeHandler for u64 {
+    async fn get_property_generic(instance: &Mpv, property: &str) -> Result<u64, Error> {
+        get_mpv_property::<u64>(instance, property).await
+    }
+}
+
+#[allow(deprecated)]
 #[async_trait]
 impl GetPropertyTypeHandler for Vec<PlaylistEntry> {
     async fn get_property_generic(
@@ -322,6 +1492,7 @@ impl GetPropertyTypeHandler for Vec<PlaylistEntry> {
     }
 }
 
+#[allow(deprecated)]
 #[async_trait]
 impl GetPropertyTypeHandler for HashMap<String, MpvDataType> {
     async fn get_property_generic(
@@ -332,11 +1503,13 @@ impl GetPropertyTypeHandler for HashMap<String, MpvDataType> {
     }
 }
 
+#[deprecated(note = "Mpv::set_property accepts any serde type, use it instead")]
 #[async_trait]
 pub trait SetPropertyTypeHandler<T> {
     async fn set_property_generic(instance: &Mpv, property: &str, value: T) -> Result<(), Error>;
 }
 
+#[allow(deprecated)]
 #[async_trait]
 impl SetPropertyTypeHandler<bool> for bool {
     async fn set_property_generic(
@@ -348,6 +1521,7 @@ impl SetPropertyTypeHandler<bool> for bool {
     }
 }
 
+#[allow(deprecated)]
 #[async_trait]
 impl SetPropertyTypeHandler<String> for String {
     async fn set_property_generic(
@@ -359,93 +1533,286 @@ impl SetPropertyTypeHandler<String> for String {
     }
 }
 
+#[allow(deprecated)]
+#[async_trait]
+impl SetPropertyTypeHandler<f64> for f64 {
+    async fn set_property_generic(instance: &Mpv, property: &str, value: f64) -> Result<(), Error> {
+        set_mpv_property::<f64>(instance, property, value).await
+    }
+}
+
+#[allow(deprecated)]
+#[async_trait]
+impl SetPropertyTypeHandler<usize> for usize {
+    async fn set_property_generic(
+        instance: &Mpv,
+        property: &str,
+        value: usize,
+    ) -> Result<(), Error> {
+        set_mpv_property::<usize>(instance, property, value).await
+    }
+}
+
+#[allow(deprecated)]
+#[async_trait]
+impl SetPropertyTypeHandler<f32> for f32 {
+    async fn set_property_generic(instance: &Mpv, property: &str, value: f32) -> Result<(), Error> {
+        set_mpv_property::<f32>(instance, property, value).await
+    }
+}
+
+#[allow(deprecated)]
+#[async_trait]
+impl SetPropertyTypeHandler<i32> for i32 {
+    async fn set_property_generic(instance: &Mpv, property: &str, value: i32) -> Result<(), Error> {
+        set_mpv_property::<i32>(instance, property, value).await
+    }
+}
+
+#[allow(deprecated)]
 #[async_trait]
-impl SetPropertyTypeHandler<f64> for f64 {
-    async fn set_property_generic(instance: &Mpv, property: &str, value: f64) -> Result<(), Error> {
-        set_mpv_property::<f64>(instance, property, value).await
+impl SetPropertyTypeHandler<i64> for i64 {
+    async fn set_property_generic(instance: &Mpv, property: &str, value: i64) -> Result<(), Error> {
+        set_mpv_property::<i64>(instance, property, value).await
     }
 }
 
+#[allow(deprecated)]
 #[async_trait]
-impl SetPropertyTypeHandler<usize> for usize {
-    async fn set_property_generic(
-        instance: &Mpv,
-        property: &str,
-        value: usize,
-    ) -> Result<(), Error> {
-        set_mpv_property::<usize>(instance, property, value).await
+impl SetPropertyTypeHandler<u32> for u32 {
+    async fn set_property_generic(instance: &Mpv, property: &str, value: u32) -> Result<(), Error> {
+        set_mpv_property::<u32>(instance, property, value).await
+    }
+}
+
+#[allow(deprecated)]
+#[async_trait]
+impl SetPropertyTypeHandler<u64> for u64 {
+    async fn set_property_generic(instance: &Mpv, property: &str, value: u64) -> Result<(), Error> {
+        set_mpv_property::<u64>(instance, property, value).await
     }
 }
 
 impl Mpv {
-    async fn start_listeners(eventtx: Sender<Event>, responsetx: Sender<Data>, stream: UnixStream) {
-        let event_clone = eventtx.clone();
-        let response_clone = responsetx.clone();
-        // tokio::task::spawn_blocking(move ||{
-        std::thread::spawn(move || {
-            let reader = BufReader::new(stream);
-            for item in Deserializer::from_reader(reader).into_iter::<MpvMessage>() {
-                match item {
-                    Ok(MpvMessage::Data(a)) => {
-                        debug!("Data: {:#?}", a);
-                        response_clone.blocking_send(a).unwrap();
-                    }
-                    Ok(MpvMessage::GenericResponse(a)) => {
-                        debug!("Generic Response: {:#?}", a);
-                        response_clone.blocking_send(a.into()).unwrap();
-                        // responsetx.send(a.into()).await.unwrap();
-                    }
-                    Ok(MpvMessage::Event(e)) => {
-                        debug!("Event: {:#?}", e);
-                        // eventtx.send(e.into()).await.unwrap();
-                        event_clone.blocking_send(e.into()).unwrap();
+    /// Moves queued events into [Mpv::event_receiver] as fast as it is read. Once the listener
+    /// has stopped for good, the remaining events are handed over and the receiver is closed.
+    fn start_forwarder(connection: Arc<Connection>, eventtx: Sender<Event>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            while let Some(event) = connection.events.pop().await {
+                if eventtx.send(event).await.is_err() {
+                    trace!("Event receiver was dropped, discarding events");
+                    connection.events.close();
+                    break;
+                }
+            }
+        })
+    }
+
+    fn start_listener(
+        connection: Arc<Connection>,
+        mut reader: OwnedReadHalf,
+        socket: String,
+        reconnect: Option<ReconnectPolicy>,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                Mpv::read_messages(&connection, reader).await;
+                // The socket is gone, so nothing will ever answer the outstanding requests.
+                // Dropping their senders wakes every waiter up with an error.
+                connection.writer.lock().await.take();
+                connection.pending_requests.lock().unwrap().clear();
+
+                let policy = match reconnect {
+                    Some(ref policy) => policy,
+                    None => break,
+                };
+                send_event(&connection, Event::Disconnected);
+                match policy.reconnect(&socket).await {
+                    Some(stream) => {
+                        debug!("Reconnected to {}", socket);
+                        let (new_reader, writer) = stream.into_split();
+                        *connection.writer.lock().await = Some(writer);
+                        reader = new_reader;
+                        tokio::spawn(Mpv::replay_observers(connection.clone()));
                     }
-                    _ => {
-                        warn!("Unhandled message: {:#?}", item);
+                    None => {
+                        warn!("Giving up reconnecting to {}", socket);
+                        break;
                     }
                 }
             }
-        });
+            close_events(&connection);
+        })
     }
 
-    pub async fn connect(socket: &str) -> Result<Mpv, Error> {
-        match UnixStream::connect(socket) {
-            Ok(stream) => {
-                let cloned_stream = stream.try_clone().expect("cloning UnixStream");
-                let (eventtx, eventrx) = tokio::sync::mpsc::channel::<Event>(8);
-                let (responsetx, responserx) = tokio::sync::mpsc::channel::<Data>(8);
-
-                Mpv::start_listeners(
-                    eventtx,
-                    responsetx,
-                    stream.try_clone().expect("cloning UnixStream"),
-                )
-                .await;
-                Ok(Mpv {
-                    stream,
-                    reader: BufReader::new(cloned_stream),
-                    name: String::from(socket),
-                    event_receiver: Some(eventrx),
-                    response_receiver: Mutex::new(responserx),
-                })
+    /// Handles everything mpv sends until the socket is closed.
+    async fn read_messages(connection: &Connection, reader: OwnedReadHalf) {
+        let mut lines = BufReader::new(reader).lines();
+        loop {
+            let line = match lines.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => break,
+                Err(why) => {
+                    warn!("Could not read from socket: {}", why);
+                    break;
+                }
+            };
+            match serde_json::from_str::<MpvMessage>(&line) {
+                Ok(MpvMessage::Data(a)) => {
+                    debug!("Data: {:#?}", a);
+                    dispatch_response(&connection.pending_requests, a);
+                }
+                Ok(MpvMessage::GenericResponse(a)) => {
+                    debug!("Generic Response: {:#?}", a);
+                    dispatch_response(&connection.pending_requests, a.into());
+                }
+                Ok(MpvMessage::Event(e)) => {
+                    debug!("Event: {:#?}", e);
+                    notify_watcher(connection, &e);
+                    send_event(connection, e.into());
+                }
+                item => {
+                    warn!("Unhandled message: {:#?}", item);
+                }
+            }
+        }
+    }
+
+    async fn replay_observers(connection: Arc<Connection>) {
+        let observed: Vec<(isize, String)> = connection
+            .observed_properties
+            .lock()
+            .unwrap()
+            .values()
+            .map(|observer| (observer.id, observer.property.clone()))
+            .collect();
+        for (id, property) in observed {
+            if let Err(why) = observe_property_on(&connection, &id, &property).await {
+                warn!("Could not observe property {} again: {}", property, why);
             }
-            Err(internal_error) => Err(Error(ErrorCode::ConnectError(internal_error.to_string()))),
         }
+        send_event(&connection, Event::Reconnected);
+    }
+
+    /// Adds the external audio, subtitle or video file _<file>_ as a new track of the current
+    /// file and returns its id. _<title>_ and _<lang>_ override what mpv would show for it.
+    ///
+    /// Use [MpvCommand::AudioRemove], [MpvCommand::SubRemove] and their `*Reload`
+    /// counterparts to get rid of or refresh the track later on.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use mpvipc::{Mpv, Error, TrackAddOptions, TrackType};
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let mpv = Mpv::connect("/tmp/mpvsocket").await?;
+    ///     let id = mpv
+    ///         .add_track(
+    ///             TrackType::Sub,
+    ///             "/tmp/movie.en.srt",
+    ///             TrackAddOptions::Auto,
+    ///             Some("Downloaded"),
+    ///             Some("eng"),
+    ///         )
+    ///         .await?;
+    ///     mpv.select_subtitle_track(id).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn add_track(
+        &self,
+        track_type: TrackType,
+        file: &str,
+        option: TrackAddOptions,
+        title: Option<&str>,
+        lang: Option<&str>,
+    ) -> Result<usize, Error> {
+        let file = file.to_string();
+        let title = title.map(str::to_string);
+        let lang = lang.map(str::to_string);
+        let command = match track_type {
+            TrackType::Audio => MpvCommand::AudioAdd {
+                file,
+                option,
+                title,
+                lang,
+            },
+            TrackType::Sub => MpvCommand::SubAdd {
+                file,
+                option,
+                title,
+                lang,
+            },
+            TrackType::Video => MpvCommand::VideoAdd {
+                file,
+                option,
+                title,
+                lang,
+            },
+        };
+        let (name, args) = command.into_args();
+        add_mpv_track(self, track_type, name, &args).await
+    }
+
+    /// Connects to the mpv socket at _<socket>_ using the default [ConnectOptions].
+    pub async fn connect(socket: &str) -> Result<Mpv, Error> {
+        ConnectOptions::default().connect(socket).await
+    }
+
+    /// Returns a [ConnectOptions] builder to configure the connection before connecting.
+    pub fn builder() -> ConnectOptions {
+        ConnectOptions::default()
     }
 
+    /// Stops listening to the socket, shuts it down and fails every command that is still
+    /// waiting for a reply. Commands sent afterwards fail with [ErrorCode::ConnectError].
     pub fn disconnect(&self) {
-        let mut stream = &self.stream;
-        stream
-            .shutdown(std::net::Shutdown::Both)
-            .expect("socket disconnect");
-        let mut buffer = [0; 32];
-        for _ in 0..stream.bytes().count() {
-            stream.read_exact(&mut buffer[..]).unwrap();
+        self.listener.abort();
+        self.forwarder.abort();
+        // Dropping the write half shuts the socket down.
+        match self.connection.writer.try_lock() {
+            Ok(mut writer) => drop(writer.take()),
+            // A command is being written right now, take the writer once it is done.
+            Err(_) => {
+                if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                    let connection = self.connection.clone();
+                    runtime.spawn(async move { connection.writer.lock().await.take() });
+                }
+            }
         }
+        self.connection.pending_requests.lock().unwrap().clear();
+        close_events(&self.connection);
+    }
+
+    pub async fn disable_subtitles(&self) -> Result<(), Error> {
+        set_mpv_property(self, "sid", "no").await
+    }
+
+    pub async fn frame_back_step(&self) -> Result<(), Error> {
+        self.run_command(MpvCommand::FrameBackStep).await
+    }
+
+    pub async fn frame_step(&self) -> Result<(), Error> {
+        self.run_command(MpvCommand::FrameStep).await
     }
 
-    pub fn get_stream_ref(&self) -> &UnixStream {
-        &self.stream
+    /// Returns the chapters of the current file, ordered by their start time.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use mpvipc::{Mpv, Error};
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let mpv = Mpv::connect("/tmp/mpvsocket").await?;
+    ///     for (index, chapter) in mpv.get_chapters().await?.iter().enumerate() {
+    ///         println!("{}: {:?} at {}s", index, chapter.title, chapter.time);
+    ///     }
+    ///     mpv.seek_chapter(2).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn get_chapters(&self) -> Result<Vec<Chapter>, Error> {
+        self.get_property("chapter-list").await
     }
 
     pub async fn get_metadata(&self) -> Result<HashMap<String, MpvDataType>, Error> {
@@ -462,37 +1829,86 @@ impl Mpv {
         }
     }
 
+    /// Returns the audio, video and subtitle tracks of the current file.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use mpvipc::{Mpv, Error, TrackType};
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let mpv = Mpv::connect("/tmp/mpvsocket").await?;
+    ///     let tracks = mpv.get_tracks().await?;
+    ///     let german = tracks.iter().find(|track| {
+    ///         track.track_type == TrackType::Audio && track.lang.as_deref() == Some("ger")
+    ///     });
+    ///     if let Some(track) = german {
+    ///         mpv.select_audio_track(track.id).await?;
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn get_tracks(&self) -> Result<Vec<Track>, Error> {
+        self.get_property("track-list").await
+    }
+
     /// # Description
     ///
     /// Retrieves the property value from mpv.
     ///
     /// ## Supported types
+    /// Any type implementing `serde::Deserialize`, e.g.
     /// - String
     /// - bool
-    /// - HashMap<String, String> (e.g. for the 'metadata' property)
+    /// - HashMap<String, MpvDataType> (e.g. for the 'metadata' property)
     /// - Vec<PlaylistEntry> (for the 'playlist' property)
-    /// - usize
-    /// - f64
+    /// - usize, u64, u32, i64, i32
+    /// - f64, f32
+    /// - your own structs (e.g. for 'track-list' or 'demuxer-cache-state')
+    ///
+    /// Fails with the matching `ValueDoesNotContain*` code (e.g.
+    /// [ErrorCode::ValueDoesNotContainBool]) if the value doesn't match String, bool, f64,
+    /// usize, HashMap<String, MpvDataType> or Vec<PlaylistEntry>, and with
+    /// [ErrorCode::JsonParseError] if it doesn't match any other `T`.
     ///
     /// ## Input arguments
     ///
     /// - **property** defines the mpv property that should be retrieved
     ///
     /// # Example
+    /// ```no_run
+    /// use mpvipc::{Mpv, Error};
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let mpv = Mpv::connect("/tmp/mpvsocket").await?;
+    ///     let paused: bool = mpv.get_property("pause").await?;
+    ///     let title: String = mpv.get_property("media-title").await?;
+    ///     Ok(())
+    /// }
     /// ```
+    ///
+    /// Decoding into your own type:
+    /// ```no_run
     /// use mpvipc::{Mpv, Error};
-    /// fn main() -> Result<(), Error> {
-    ///     let mpv = Mpv::connect("/tmp/mpvsocket")?;
-    ///     let paused: bool = mpv.get_property("pause")?;
-    ///     let title: String = mpv.get_property("media-title")?;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Edition {
+    ///     id: usize,
+    ///     title: Option<String>,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let mpv = Mpv::connect("/tmp/mpvsocket").await?;
+    ///     let editions: Vec<Edition> = mpv.get_property("edition-list").await?;
     ///     Ok(())
     /// }
     /// ```
-    pub async fn get_property<T: GetPropertyTypeHandler>(
+    pub async fn get_property<T: DeserializeOwned + 'static>(
         &self,
         property: &str,
     ) -> Result<T, Error> {
-        T::get_property_generic(self, property).await
+        get_mpv_property_deserialized(self, property).await
     }
 
     /// # Description
@@ -506,11 +1922,12 @@ impl Mpv {
     ///
     /// # Example
     ///
-    /// ```
+    /// ```no_run
     /// use mpvipc::{Mpv, Error};
-    /// fn main() -> Result<(), Error> {
-    ///     let mpv = Mpv::connect("/tmp/mpvsocket")?;
-    ///     let title = mpv.get_property_string("media-title")?;
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let mpv = Mpv::connect("/tmp/mpvsocket").await?;
+    ///     let title = mpv.get_property_string("media-title").await?;
     ///     Ok(())
     /// }
     /// ```
@@ -518,37 +1935,78 @@ impl Mpv {
         get_mpv_property_string(self, property).await
     }
 
-    pub async fn kill(&self) -> Result<(), Error> {
-        self.run_command(MpvCommand::Quit).await
+    /// Same as [Mpv::get_property], but waits at most _<timeout>_ for the reply
+    /// instead of the connection's default command timeout.
+    pub async fn get_property_with_timeout<T: DeserializeOwned + 'static>(
+        &self,
+        property: &str,
+        timeout: Duration,
+    ) -> Result<T, Error> {
+        with_command_timeout(timeout, self.get_property(property)).await
     }
 
-    /// # Description
-    ///
-    /// Waits until an mpv event occurs and returns the Event.
-    ///
-    /// # Example
-    ///
-    /// ```ignore
-    /// let mut mpv = Mpv::connect("/tmp/mpvsocket")?;
-    /// loop {
-    ///     let event = mpv.event_listen()?;
-    ///     println!("{:?}", event);
-    /// }
-    /// ```
-    // pub async fn event_listen(&mut self) -> Result<Event, Error> {
-    //     listen(self).await
-    // }
-
-    pub fn event_listen_raw(&mut self) -> String {
-        listen_raw(self)
+    pub async fn kill(&self) -> Result<(), Error> {
+        self.run_command(MpvCommand::Quit).await
     }
 
     pub async fn next(&self) -> Result<(), Error> {
         self.run_command(MpvCommand::PlaylistNext).await
     }
 
+    pub async fn next_chapter(&self) -> Result<(), Error> {
+        run_mpv_command(self, "add", &[json!("chapter"), json!(1)]).await
+    }
+
     pub async fn observe_property(&self, id: &isize, property: &str) -> Result<(), Error> {
-        observe_mpv_property(self, id, property).await
+        self.observe(id, property, None).await
+    }
+
+    async fn observe(
+        &self,
+        id: &isize,
+        property: &str,
+        value_type: Option<&'static str>,
+    ) -> Result<(), Error> {
+        observe_mpv_property(self, id, property).await?;
+        let observer = Observer {
+            id: *id,
+            property: property.to_string(),
+            value_type,
+        };
+        self.connection
+            .observed_properties
+            .lock()
+            .unwrap()
+            .insert(*id, observer);
+        Ok(())
+    }
+
+    /// Returns the property observations that are currently registered, ordered by id.
+    pub fn observers(&self) -> Vec<Observer> {
+        let mut observers: Vec<Observer> = self
+            .connection
+            .observed_properties
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect();
+        observers.sort_by_key(|observer| observer.id);
+        observers
+    }
+
+    /// Stops every property observation, see [Mpv::unobserve_property].
+    /// All observations are attempted; the first error is returned.
+    pub async fn clear_observers(&self) -> Result<(), Error> {
+        let mut result = Ok(());
+        for observer in self.observers() {
+            if let Err(why) = self.unobserve_property(&observer.id).await {
+                if result.is_ok() {
+                    result = Err(why);
+                }
+            }
+        }
+        result
     }
 
     pub async fn pause(&self) -> Result<(), Error> {
@@ -559,14 +2017,23 @@ impl Mpv {
         self.run_command(MpvCommand::PlaylistPrev).await
     }
 
+    pub async fn prev_chapter(&self) -> Result<(), Error> {
+        run_mpv_command(self, "add", &[json!("chapter"), json!(-1)]).await
+    }
+
     pub async fn restart(&self) -> Result<(), Error> {
         self.run_command(MpvCommand::Seek {
             seconds: 0f64,
             option: SeekOptions::Absolute,
+            precision: SeekPrecision::Default,
         })
         .await
     }
 
+    pub async fn revert_seek(&self, option: RevertSeekOptions) -> Result<(), Error> {
+        self.run_command(MpvCommand::RevertSeek(option)).await
+    }
+
     /// # Description
     ///
     /// Runs mpv commands. The arguments are passed as a String-Vector reference:
@@ -577,88 +2044,174 @@ impl Mpv {
     /// - **args**      a slice of &str's which define the arguments
     ///
     /// # Example
-    /// ```
-    /// use mpvipc::{Mpv, Error};
-    /// fn main() -> Result<(), Error> {
-    ///     let mpv = Mpv::connect("/tmp/mpvsocket")?;
+    /// ```no_run
+    /// use mpvipc::{Mpv, MpvCommand, SeekOptions, SeekPrecision, Error};
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let mpv = Mpv::connect("/tmp/mpvsocket").await?;
     ///
     ///     //Run command 'playlist-shuffle' which takes no arguments
-    ///     mpv.run_command(MpvCommand::PlaylistShuffle)?;
+    ///     mpv.run_command(MpvCommand::PlaylistShuffle).await?;
     ///
     ///     //Run command 'seek' which in this case takes two arguments
     ///     mpv.run_command(MpvCommand::Seek {
     ///         seconds: 0f64,
     ///         option: SeekOptions::Absolute,
-    ///     })?;
+    ///         precision: SeekPrecision::Default,
+    ///     }).await?;
     ///     Ok(())
     /// }
     /// ```
     pub async fn run_command(&self, command: MpvCommand) -> Result<(), Error> {
-        match command {
-            MpvCommand::LoadFile { file, option } => {
-                run_mpv_command(
-                    self,
-                    "loadfile",
-                    &[
-                        file.as_ref(),
-                        match option {
-                            PlaylistAddOptions::Append => "append",
-                            PlaylistAddOptions::Replace => "replace",
-                        },
-                    ],
-                )
-                .await
-            }
-            MpvCommand::LoadList { file, option } => {
-                run_mpv_command(
-                    self,
-                    "loadlist",
-                    &[
-                        file.as_ref(),
-                        match option {
-                            PlaylistAddOptions::Append => "append",
-                            PlaylistAddOptions::Replace => "replace",
-                        },
-                    ],
-                )
-                .await
-            }
-            MpvCommand::PlaylistClear => run_mpv_command(self, "playlist-clear", &[]).await,
-            MpvCommand::PlaylistMove { from, to } => {
-                run_mpv_command(self, "playlist-move", &[&from.to_string(), &to.to_string()]).await
-            }
-            MpvCommand::PlaylistNext => run_mpv_command(self, "playlist-next", &[]).await,
-            MpvCommand::PlaylistPrev => run_mpv_command(self, "playlist-prev", &[]).await,
-            MpvCommand::PlaylistRemove(id) => {
-                run_mpv_command(self, "playlist-remove", &[&id.to_string()]).await
-            }
-            MpvCommand::PlaylistShuffle => run_mpv_command(self, "playlist-shuffle", &[]).await,
-            MpvCommand::Quit => run_mpv_command(self, "quit", &[]).await,
-            MpvCommand::Seek { seconds, option } => {
-                run_mpv_command(
-                    self,
-                    "seek",
-                    &[
-                        &seconds.to_string(),
-                        match option {
-                            SeekOptions::Absolute => "absolute",
-                            SeekOptions::Relative => "relative",
-                            SeekOptions::AbsolutePercent => "absolute-percent",
-                            SeekOptions::RelativePercent => "relative-percent",
-                        },
-                    ],
-                )
-                .await
-            }
-            MpvCommand::Stop => run_mpv_command(self, "stop", &[]).await,
-        }
+        let (name, args) = command.into_args();
+        run_mpv_command(self, name, &args).await
+    }
+
+    /// # Description
+    ///
+    /// Starts an mpv command with `"async": true` and returns without waiting for it to finish.
+    /// Use this for slow commands (e.g. `screenshot-to-file` or `subprocess`), so they don't
+    /// hold up other requests. The returned [AsyncCommand] resolves once mpv reports completion.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use mpvipc::{Mpv, MpvCommand, PlaylistAddOptions, Error};
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let mpv = Mpv::connect("/tmp/mpvsocket").await?;
+    ///     let loading = mpv.run_command_async(MpvCommand::LoadFile {
+    ///         file: "video.mkv".to_string(),
+    ///         option: PlaylistAddOptions::Append,
+    ///     }).await?;
+    ///     mpv.pause().await?;
+    ///     loading.await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn run_command_async(&self, command: MpvCommand) -> Result<AsyncCommand, Error> {
+        let (name, args) = command.into_args();
+        start_async_command(&self.connection, &positional_command(name, &args)).await
+    }
+
+    /// # Description
+    ///
+    /// Runs an mpv command like [Mpv::run_command] and returns the data of mpv's reply,
+    /// decoded as `T`. Use [MpvDataType] if the shape of the result isn't known up front.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use mpvipc::{Mpv, MpvCommand, MpvDataType, Error};
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let mpv = Mpv::connect("/tmp/mpvsocket").await?;
+    ///     let result: MpvDataType = mpv.run_command_with_result(MpvCommand::PlaylistNext).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn run_command_with_result<T: TypeHandler>(
+        &self,
+        command: MpvCommand,
+    ) -> Result<T, Error> {
+        let (name, args) = command.into_args();
+        run_mpv_command_with_result(self, name, &args).await
+    }
+
+    /// Same as [Mpv::run_command], but waits at most _<timeout>_ for the reply
+    /// instead of the connection's default command timeout.
+    pub async fn run_command_with_timeout(
+        &self,
+        command: MpvCommand,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        with_command_timeout(timeout, self.run_command(command)).await
     }
 
     /// Run a custom command.
     /// This should only be used if the desired command is not implemented
     /// with [MpvCommand].
     pub async fn run_command_raw(&self, command: &str, args: &[&str]) -> Result<(), Error> {
-        run_mpv_command(self, command, args).await
+        let args: Vec<Value> = args.iter().map(|arg| json!(arg)).collect();
+        run_mpv_command(self, command, &args).await
+    }
+
+    /// # Description
+    ///
+    /// Runs a custom command like [Mpv::run_command_raw] and returns the data of mpv's reply,
+    /// decoded as `T`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use mpvipc::{Mpv, Error};
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let mpv = Mpv::connect("/tmp/mpvsocket").await?;
+    ///     let title: String = mpv
+    ///         .run_command_raw_with_result("expand-text", &["${media-title}"])
+    ///         .await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn run_command_raw_with_result<T: TypeHandler>(
+        &self,
+        command: &str,
+        args: &[&str],
+    ) -> Result<T, Error> {
+        let args: Vec<Value> = args.iter().map(|arg| json!(arg)).collect();
+        run_mpv_command_with_result(self, command, &args).await
+    }
+
+    /// Same as [Mpv::run_command_raw], but runs the command asynchronously like
+    /// [Mpv::run_command_async].
+    pub async fn run_command_raw_async(
+        &self,
+        command: &str,
+        args: &[&str],
+    ) -> Result<AsyncCommand, Error> {
+        let args: Vec<Value> = args.iter().map(|arg| json!(arg)).collect();
+        start_async_command(&self.connection, &positional_command(command, &args)).await
+    }
+
+    /// # Description
+    ///
+    /// Runs an mpv command using named arguments, e.g.
+    /// `{"name": "loadfile", "url": "video.mkv", "flags": "append"}`.
+    /// Arguments that are left out use mpv's defaults.
+    ///
+    /// ## Input arguments
+    ///
+    /// - **name**  defines the mpv command that should be executed
+    /// - **args**  maps the argument names to their values
+    ///
+    /// # Example
+    /// ```no_run
+    /// use mpvipc::{Mpv, MpvDataType, Error};
+    /// use std::collections::HashMap;
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let mpv = Mpv::connect("/tmp/mpvsocket").await?;
+    ///     let mut args = HashMap::new();
+    ///     args.insert("url".to_string(), MpvDataType::String("video.mkv".to_string()));
+    ///     args.insert("flags".to_string(), MpvDataType::String("append".to_string()));
+    ///     mpv.run_command_named("loadfile", args).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn run_command_named(
+        &self,
+        name: &str,
+        args: HashMap<String, MpvDataType>,
+    ) -> Result<(), Error> {
+        run_mpv_command_named(self, name, args).await
+    }
+
+    /// Same as [Mpv::run_command_named], but runs the command asynchronously like
+    /// [Mpv::run_command_async].
+    pub async fn run_command_named_async(
+        &self,
+        name: &str,
+        args: HashMap<String, MpvDataType>,
+    ) -> Result<AsyncCommand, Error> {
+        start_async_command(&self.connection, &named_command(name, args)).await
     }
 
     pub async fn playlist_add(
@@ -721,7 +2274,45 @@ impl Mpv {
     }
 
     pub async fn seek(&self, seconds: f64, option: SeekOptions) -> Result<(), Error> {
-        self.run_command(MpvCommand::Seek { seconds, option }).await
+        self.seek_with_precision(seconds, option, SeekPrecision::Default)
+            .await
+    }
+
+    /// Jumps to the start of the chapter at _<index>_ in [Mpv::get_chapters].
+    pub async fn seek_chapter(&self, index: usize) -> Result<(), Error> {
+        set_mpv_property(self, "chapter", index).await
+    }
+
+    /// Seeks to the absolute position _<timestamp>_, e.g. `"01:02:03.5"`, see [parse_timestamp].
+    pub async fn seek_to_timestamp(&self, timestamp: &str) -> Result<(), Error> {
+        let seconds = parse_timestamp(timestamp)?;
+        self.seek(seconds, SeekOptions::Absolute).await
+    }
+
+    pub async fn seek_with_precision(
+        &self,
+        seconds: f64,
+        option: SeekOptions,
+        precision: SeekPrecision,
+    ) -> Result<(), Error> {
+        self.run_command(MpvCommand::Seek {
+            seconds,
+            option,
+            precision,
+        })
+        .await
+    }
+
+    pub async fn select_audio_track(&self, id: usize) -> Result<(), Error> {
+        set_mpv_property(self, "aid", id).await
+    }
+
+    pub async fn select_subtitle_track(&self, id: usize) -> Result<(), Error> {
+        set_mpv_property(self, "sid", id).await
+    }
+
+    pub async fn select_video_track(&self, id: usize) -> Result<(), Error> {
+        set_mpv_property(self, "vid", id).await
     }
 
     pub async fn set_loop_file(&self, option: Switch) -> Result<(), Error> {
@@ -784,31 +2375,40 @@ impl Mpv {
     /// Sets the mpv property _<property>_ to _<value>_.
     ///
     /// ## Supported types
-    /// - String
-    /// - bool
-    /// - f64
-    /// - usize
+    /// Any type implementing `serde::Serialize`, e.g. String, bool, numbers,
+    /// or a `Vec` or `HashMap` of them for list and map properties.
     ///
     /// ## Input arguments
     ///
     /// - **property** defines the mpv property that should be retrieved
     /// - **value** defines the value of the given mpv property _<property>_
     ///
+    /// Fails with [ErrorCode::MpvError] if mpv rejects the value, e.g. because the
+    /// property is read-only or the value is out of range (see [Error::kind]).
+    ///
     /// # Example
-    /// ```
+    /// ```no_run
     /// use mpvipc::{Mpv, Error};
-    /// fn main() -> Result<(), Error> {
-    ///     let mpv = Mpv::connect("/tmp/mpvsocket")?;
-    ///     mpv.set_property("pause", true)?;
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let mpv = Mpv::connect("/tmp/mpvsocket").await?;
+    ///     mpv.set_property("pause", true).await?;
     ///     Ok(())
     /// }
     /// ```
-    pub async fn set_property<T: SetPropertyTypeHandler<T>>(
+    pub async fn set_property<T: Serialize>(&self, property: &str, value: T) -> Result<(), Error> {
+        set_mpv_property(self, property, value).await
+    }
+
+    /// Same as [Mpv::set_property], but waits at most _<timeout>_ for the reply
+    /// instead of the connection's default command timeout.
+    pub async fn set_property_with_timeout<T: Serialize>(
         &self,
         property: &str,
         value: T,
+        timeout: Duration,
     ) -> Result<(), Error> {
-        T::set_property_generic(self, property, value).await
+        with_command_timeout(timeout, self.set_property(property, value)).await
     }
 
     pub async fn set_speed(
@@ -859,10 +2459,184 @@ impl Mpv {
         self.run_command(MpvCommand::Stop).await
     }
 
+    /// Returns a new [Subscription] that receives every event from now on,
+    /// independently of [Mpv::event_receiver] and of other subscriptions.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use mpvipc::{Error, Mpv};
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let mpv = Mpv::connect("/tmp/mpvsocket").await?;
+    ///     mpv.observe_property(&1, "volume").await?;
+    ///     let mut volume = mpv.subscribe().property("volume");
+    ///     let event = volume.recv().await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn subscribe(&self) -> Subscription {
+        let receiver = match self.connection.subscribers.lock().unwrap().as_ref() {
+            Some(subscribers) => subscribers.subscribe(),
+            // The listener has stopped, so the subscription ends right away.
+            None => broadcast::channel(1).1,
+        };
+        Subscription {
+            receiver,
+            kinds: Vec::new(),
+            properties: Vec::new(),
+        }
+    }
+
+    /// Seeks to the subtitle line _<lines>_ lines away from the current one.
+    pub async fn sub_seek(&self, lines: isize) -> Result<(), Error> {
+        self.run_command(MpvCommand::SubSeek(lines)).await
+    }
+
     pub async fn toggle(&self) -> Result<(), Error> {
         match get_mpv_property::<bool>(self, "pause").await {
             Ok(paused) => set_mpv_property(self, "pause", !paused).await,
             Err(msg) => Err(msg),
         }
     }
+
+    /// Stops observing the property registered with _<id>_. A [PropertyStream] using
+    /// the id ends.
+    pub async fn unobserve_property(&self, id: &isize) -> Result<(), Error> {
+        unobserve_mpv_property(self, id).await?;
+        self.connection
+            .observed_properties
+            .lock()
+            .unwrap()
+            .remove(id);
+        self.connection.watchers.lock().unwrap().remove(id);
+        Ok(())
+    }
+
+    /// Observes _<property>_ and returns a [PropertyStream] of its values decoded as `T`.
+    /// The observer id is allocated automatically and released when the stream is dropped.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use mpvipc::{Error, Mpv};
+    /// use tokio_stream::StreamExt;
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let mpv = Mpv::connect("/tmp/mpvsocket").await?;
+    ///     let mut volume = mpv.watch::<f64>("volume").await?;
+    ///     while let Some(volume) = volume.next().await {
+    ///         println!("Volume: {}", volume?);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn watch<T: TypeHandler>(&self, property: &str) -> Result<PropertyStream<T>, Error> {
+        let id = self
+            .connection
+            .next_watch_id
+            .fetch_sub(1, Ordering::Relaxed);
+        let (sender, receiver) = watch::channel(None);
+        // Registered first, mpv sends the current value as soon as the property is observed.
+        self.connection.watchers.lock().unwrap().insert(id, sender);
+        let value_type = Some(std::any::type_name::<T>());
+        if let Err(why) = self.observe(&id, property, value_type).await {
+            self.connection.watchers.lock().unwrap().remove(&id);
+            return Err(why);
+        }
+        Ok(PropertyStream {
+            values: WatchStream::new(receiver),
+            connection: Arc::downgrade(&self.connection),
+            id,
+            property: property.to_string(),
+            _type: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn volume(id: isize, volume: f64) -> Event {
+        Event::PropertyChange {
+            id,
+            property: Property::Volume(volume),
+        }
+    }
+
+    /// The queued events, with property changes shown as `id=value`.
+    fn queued(queue: &EventQueue) -> Vec<String> {
+        queue
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|event| match event {
+                Event::PropertyChange {
+                    id,
+                    property: Property::Volume(volume),
+                } => format!("{}={}", id, volume),
+                event => event.name().to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn drop_oldest_keeps_the_latest_events() {
+        let queue = EventQueue::new(2, OverflowPolicy::DropOldest);
+        queue.push(Event::Idle);
+        queue.push(volume(1, 10.0));
+        queue.push(volume(1, 20.0));
+        assert_eq!(queued(&queue), ["1=10", "1=20"]);
+    }
+
+    #[test]
+    fn drop_newest_keeps_the_earliest_events() {
+        let queue = EventQueue::new(2, OverflowPolicy::DropNewest);
+        queue.push(Event::Idle);
+        queue.push(volume(1, 10.0));
+        queue.push(volume(1, 20.0));
+        assert_eq!(queued(&queue), ["idle", "1=10"]);
+    }
+
+    #[test]
+    fn coalescing_replaces_the_last_change_of_the_same_property() {
+        let queue = EventQueue::new(3, OverflowPolicy::CoalesceProperties);
+        queue.push(volume(1, 10.0));
+        // Not full yet, so nothing is coalesced.
+        queue.push(volume(1, 20.0));
+        queue.push(volume(2, 1.0));
+        queue.push(volume(1, 30.0));
+        assert_eq!(queued(&queue), ["1=10", "1=30", "2=1"]);
+        queue.push(volume(2, 2.0));
+        assert_eq!(queued(&queue), ["1=10", "1=30", "2=2"]);
+    }
+
+    #[test]
+    fn coalescing_drops_the_oldest_event_otherwise() {
+        let queue = EventQueue::new(2, OverflowPolicy::CoalesceProperties);
+        queue.push(volume(1, 10.0));
+        queue.push(Event::Idle);
+        queue.push(volume(2, 1.0));
+        assert_eq!(queued(&queue), ["idle", "2=1"]);
+        queue.push(Event::Seek);
+        assert_eq!(queued(&queue), ["2=1", "seek"]);
+    }
+
+    #[test]
+    fn closed_queues_discard_events() {
+        let queue = EventQueue::new(2, OverflowPolicy::DropOldest);
+        queue.push(Event::Idle);
+        queue.close();
+        queue.push(Event::Seek);
+        assert!(queued(&queue).is_empty());
+    }
+
+    #[tokio::test]
+    async fn finished_queues_hand_out_the_rest_and_end() {
+        let queue = EventQueue::new(2, OverflowPolicy::DropOldest);
+        queue.push(Event::Idle);
+        queue.finish();
+        assert!(matches!(queue.pop().await, Some(Event::Idle)));
+        assert!(queue.pop().await.is_none());
+    }
 }
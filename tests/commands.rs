@@ -0,0 +1,230 @@
+mod common;
+
+use common::{Client, FakeMpv, PATIENCE};
+use mpvipc::{
+    Error, ErrorCode, Mpv, MpvCommand, MpvDataType, MpvErrorKind, PlaylistAddOptions,
+    RevertSeekOptions, SeekOptions, SeekPrecision, TrackAddOptions, TrackType,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Runs _<command>_ and returns the request mpv received for it.
+async fn run(mpv: &Mpv, client: &mut Client, command: MpvCommand) -> Value {
+    let server = async {
+        let request = client.request().await.unwrap();
+        client.reply(&request, json!(null)).await;
+        request
+    };
+    let (request, result) = tokio::join!(server, mpv.run_command(command));
+    result.unwrap();
+    request
+}
+
+#[tokio::test]
+async fn arguments_are_sent_as_json_values() {
+    let fake = FakeMpv::bind();
+    let mpv = Mpv::connect(fake.socket()).await.unwrap();
+    let mut client = fake.accept().await;
+
+    let path = "/tmp/a \"quoted\" \\ file\nwith a newline.mkv";
+    let command = MpvCommand::LoadFile {
+        file: path.to_string(),
+        option: PlaylistAddOptions::Append,
+    };
+    let request = run(&mpv, &mut client, command).await;
+    assert_eq!(request["command"], json!(["loadfile", path, "append"]));
+    // The newline in the path did not split the request: the next line is the next command.
+
+    let command = MpvCommand::Seek {
+        seconds: -2.5,
+        option: SeekOptions::Relative,
+        precision: SeekPrecision::Default,
+    };
+    let request = run(&mpv, &mut client, command).await;
+    assert_eq!(request["command"], json!(["seek", -2.5, "relative"]));
+
+    let command = MpvCommand::PlaylistMove { from: 3, to: 0 };
+    let request = run(&mpv, &mut client, command).await;
+    assert_eq!(request["command"], json!(["playlist-move", 3, 0]));
+}
+
+#[tokio::test]
+async fn added_tracks_take_their_id_from_the_reply() {
+    let fake = FakeMpv::bind();
+    let mpv = Mpv::connect(fake.socket()).await.unwrap();
+    let mut client = fake.accept().await;
+
+    let server = async {
+        let request = client.request().await.unwrap();
+        client.reply(&request, json!({"track_id": 4})).await;
+        request
+    };
+    let added = mpv.add_track(
+        TrackType::Sub,
+        "/tmp/movie.srt",
+        TrackAddOptions::Auto,
+        None,
+        Some("eng"),
+    );
+    let (request, id) = tokio::join!(server, added);
+    assert_eq!(
+        request["command"],
+        json!(["sub-add", "/tmp/movie.srt", "auto", "", "eng"])
+    );
+    assert_eq!(id.unwrap(), 4);
+}
+
+#[tokio::test]
+async fn added_tracks_are_looked_up_in_the_track_list() {
+    let fake = FakeMpv::bind();
+    let mpv = Mpv::connect(fake.socket()).await.unwrap();
+    let mut client = fake.accept().await;
+
+    let server = async {
+        let request = client.request().await.unwrap();
+        assert_eq!(
+            request["command"],
+            json!(["audio-add", "/tmp/dub.flac", "select"])
+        );
+        client.reply(&request, json!(null)).await;
+        let request = client.request().await.unwrap();
+        assert_eq!(request["command"], json!(["get_property", "track-list"]));
+        let tracks = json!([
+            {"id": 1, "type": "audio", "external": false},
+            {"id": 2, "type": "audio", "external": true, "external-filename": "/tmp/dub.flac"},
+            {"id": 3, "type": "sub", "external": true, "external-filename": "/tmp/dub.flac"},
+            {"id": 5, "type": "audio", "external": true, "external-filename": "/tmp/dub.flac"},
+            {"id": 6, "type": "audio", "external": true, "external-filename": "/tmp/other.flac"},
+        ]);
+        client.reply(&request, tracks).await;
+    };
+    let added = mpv.add_track(
+        TrackType::Audio,
+        "/tmp/dub.flac",
+        TrackAddOptions::Select,
+        None,
+        None,
+    );
+    let (_, id) = tokio::join!(server, added);
+    assert_eq!(id.unwrap(), 5);
+}
+
+#[tokio::test]
+async fn seek_commands_carry_their_flags() {
+    let fake = FakeMpv::bind();
+    let mpv = Mpv::connect(fake.socket()).await.unwrap();
+    let mut client = fake.accept().await;
+
+    let commands = vec![
+        (
+            MpvCommand::Seek {
+                seconds: 90.0,
+                option: SeekOptions::Absolute,
+                precision: SeekPrecision::Exact,
+            },
+            json!(["seek", 90.0, "absolute+exact"]),
+        ),
+        (
+            MpvCommand::Seek {
+                seconds: 10.0,
+                option: SeekOptions::Relative,
+                precision: SeekPrecision::Keyframes,
+            },
+            json!(["seek", 10.0, "relative+keyframes"]),
+        ),
+        (
+            MpvCommand::Seek {
+                seconds: 0.0,
+                option: SeekOptions::Absolute,
+                precision: SeekPrecision::Default,
+            },
+            json!(["seek", 0.0, "absolute"]),
+        ),
+        (
+            MpvCommand::RevertSeek(RevertSeekOptions::Mark),
+            json!(["revert-seek", "mark"]),
+        ),
+        (MpvCommand::SubSeek(-1), json!(["sub-seek", -1])),
+    ];
+    for (command, expected) in commands {
+        let request = run(&mpv, &mut client, command).await;
+        assert_eq!(request["command"], expected);
+    }
+}
+
+#[tokio::test]
+async fn async_commands_finish_after_later_requests() {
+    let fake = FakeMpv::bind();
+    let mpv = Mpv::connect(fake.socket()).await.unwrap();
+    let mut client = fake.accept().await;
+
+    let mut args = HashMap::new();
+    args.insert(
+        "url".to_string(),
+        MpvDataType::String("/tmp/a.mkv".to_string()),
+    );
+    let server = async {
+        let request = client.request().await.unwrap();
+        client.reply(&request, json!(null)).await;
+        request
+    };
+    let (request, result) = tokio::join!(server, mpv.run_command_named("loadfile", args));
+    result.unwrap();
+    assert_eq!(request.get("async"), None);
+    assert_eq!(
+        request["command"],
+        json!({"name": "loadfile", "url": "/tmp/a.mkv"})
+    );
+
+    let mut args = HashMap::new();
+    args.insert(
+        "filename".to_string(),
+        MpvDataType::String("/tmp/shot.png".to_string()),
+    );
+    let (started, request) = tokio::join!(
+        mpv.run_command_named_async("screenshot-to-file", args),
+        client.request(),
+    );
+    let mut screenshot = started.unwrap();
+    let screenshot_request = request.unwrap();
+    assert_eq!(screenshot_request["async"], json!(true));
+    assert_eq!(
+        screenshot_request["command"],
+        json!({"name": "screenshot-to-file", "filename": "/tmp/shot.png"})
+    );
+
+    // mpv answers the plain command while the screenshot is still being taken.
+    let request = run(&mpv, &mut client, MpvCommand::PlaylistNext).await;
+    assert_eq!(request.get("async"), None);
+    let pending = tokio::time::timeout(Duration::from_millis(50), &mut screenshot).await;
+    assert!(pending.is_err());
+
+    client.reply(&screenshot_request, json!(null)).await;
+    let finished = tokio::time::timeout(PATIENCE, screenshot).await.unwrap();
+    assert!(finished.is_ok());
+
+    let command = MpvCommand::LoadFile {
+        file: "/tmp/missing.mkv".to_string(),
+        option: PlaylistAddOptions::Replace,
+    };
+    let (started, request) = tokio::join!(mpv.run_command_async(command), client.request());
+    let loading = started.unwrap();
+    let request = request.unwrap();
+    assert_eq!(request["async"], json!(true));
+    client
+        .send(json!({
+            "request_id": request["request_id"],
+            "error": "error running command",
+            "data": null,
+        }))
+        .await;
+    let result = tokio::time::timeout(PATIENCE, loading).await.unwrap();
+    match result {
+        Err(Error(ErrorCode::MpvError { kind, context })) => {
+            assert_eq!(kind, MpvErrorKind::CommandError);
+            assert_eq!(context.as_deref(), Some("command loadfile"));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
@@ -0,0 +1,86 @@
+//! A fake mpv that tests drive by hand: it reads requests and writes replies and events.
+
+#![allow(dead_code)]
+
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::UnixListener;
+
+/// How long a test waits for something that should happen right away.
+pub const PATIENCE: Duration = Duration::from_secs(5);
+
+pub struct FakeMpv {
+    pub path: PathBuf,
+    listener: UnixListener,
+}
+
+/// One client connection accepted by [FakeMpv].
+pub struct Client {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    writer: OwnedWriteHalf,
+}
+
+impl FakeMpv {
+    pub fn bind() -> FakeMpv {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "mpvipc-test-{}-{}.sock",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        FakeMpv { path, listener }
+    }
+
+    pub fn socket(&self) -> &str {
+        self.path.to_str().unwrap()
+    }
+
+    pub async fn accept(&self) -> Client {
+        let (stream, _) = tokio::time::timeout(PATIENCE, self.listener.accept())
+            .await
+            .expect("no client connected")
+            .unwrap();
+        let (reader, writer) = stream.into_split();
+        Client {
+            lines: BufReader::new(reader).lines(),
+            writer,
+        }
+    }
+}
+
+impl Drop for FakeMpv {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+impl Client {
+    /// The next request sent by the client, `None` once it closed the connection.
+    pub async fn request(&mut self) -> Option<Value> {
+        let line = tokio::time::timeout(PATIENCE, self.lines.next_line())
+            .await
+            .expect("no request arrived")
+            .unwrap()?;
+        Some(serde_json::from_str(&line).unwrap())
+    }
+
+    pub async fn reply(&mut self, request: &Value, data: Value) {
+        self.send(json!({
+            "request_id": request["request_id"],
+            "error": "success",
+            "data": data,
+        }))
+        .await;
+    }
+
+    pub async fn send(&mut self, message: Value) {
+        let line = format!("{}\n", message);
+        self.writer.write_all(line.as_bytes()).await.unwrap();
+    }
+}
@@ -0,0 +1,161 @@
+mod common;
+
+use common::{FakeMpv, PATIENCE};
+use mpvipc::{Error, ErrorCode, Event, Mpv, ReconnectPolicy};
+use serde_json::json;
+use std::time::Duration;
+
+#[tokio::test]
+async fn commands_fail_after_disconnect() {
+    let fake = FakeMpv::bind();
+    let mpv = Mpv::connect(fake.socket()).await.unwrap();
+    let mut client = fake.accept().await;
+
+    mpv.disconnect();
+    let result = tokio::time::timeout(PATIENCE, mpv.get_property::<bool>("pause"))
+        .await
+        .expect("command hung after disconnect");
+    assert!(matches!(result, Err(Error(ErrorCode::ConnectError(_)))));
+    assert!(client.request().await.is_none());
+}
+
+#[tokio::test]
+async fn zero_capacities_are_raised_to_one() {
+    let fake = FakeMpv::bind();
+    let mpv = Mpv::builder()
+        .event_capacity(0)
+        .response_capacity(0)
+        .command_timeout(PATIENCE)
+        .connect(fake.socket())
+        .await
+        .unwrap();
+    let mut client = fake.accept().await;
+
+    let server = async {
+        let request = client.request().await.unwrap();
+        client.reply(&request, json!(true)).await;
+    };
+    let (_, paused) = tokio::join!(server, mpv.get_property::<bool>("pause"));
+    assert!(paused.unwrap());
+}
+
+#[tokio::test]
+async fn commands_time_out_while_waiting_for_the_reply() {
+    let fake = FakeMpv::bind();
+    let mpv = Mpv::builder()
+        .command_timeout(Duration::from_millis(50))
+        .connect(fake.socket())
+        .await
+        .unwrap();
+    let mut client = fake.accept().await;
+
+    let result = mpv.get_property::<bool>("pause").await;
+    assert!(matches!(result, Err(Error(ErrorCode::Timeout))));
+    // The request went out in one piece and the next one follows on its own line.
+    let first = client.request().await.unwrap();
+    assert_eq!(first["command"], json!(["get_property", "pause"]));
+    let server = async {
+        let request = client.request().await.unwrap();
+        client.reply(&request, json!(false)).await;
+    };
+    let (_, muted) = tokio::join!(server, mpv.get_property::<bool>("mute"));
+    assert!(!muted.unwrap());
+}
+
+#[tokio::test]
+async fn subscriptions_end_once_mpv_hangs_up() {
+    let fake = FakeMpv::bind();
+    let mpv = Mpv::connect(fake.socket()).await.unwrap();
+    let mut subscription = mpv.subscribe();
+    let mut client = fake.accept().await;
+
+    client.send(json!({"event": "idle"})).await;
+    drop(client);
+    let event = tokio::time::timeout(PATIENCE, subscription.recv()).await;
+    assert!(matches!(event.unwrap(), Ok(Event::Idle)));
+    let end = tokio::time::timeout(PATIENCE, subscription.recv()).await;
+    assert!(matches!(
+        end.unwrap(),
+        Err(Error(ErrorCode::ConnectError(_)))
+    ));
+
+    let late = tokio::time::timeout(PATIENCE, mpv.subscribe().recv()).await;
+    assert!(matches!(
+        late.unwrap(),
+        Err(Error(ErrorCode::ConnectError(_)))
+    ));
+}
+
+#[tokio::test]
+async fn event_receiver_closes_once_mpv_hangs_up() {
+    let fake = FakeMpv::bind();
+    let mut mpv = Mpv::connect(fake.socket()).await.unwrap();
+    let mut events = mpv.event_receiver.take().unwrap();
+    let mut client = fake.accept().await;
+
+    client.send(json!({"event": "idle"})).await;
+    drop(client);
+    let event = tokio::time::timeout(PATIENCE, events.recv()).await.unwrap();
+    assert!(matches!(event, Some(Event::Idle)));
+    let end = tokio::time::timeout(PATIENCE, events.recv()).await.unwrap();
+    assert!(end.is_none());
+}
+
+#[tokio::test]
+async fn event_receiver_closes_once_reconnecting_gives_up() {
+    let fake = FakeMpv::bind();
+    let mut mpv = Mpv::builder()
+        .reconnect(ReconnectPolicy {
+            initial_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(10),
+            max_attempts: Some(2),
+        })
+        .connect(fake.socket())
+        .await
+        .unwrap();
+    let mut events = mpv.event_receiver.take().unwrap();
+    let client = fake.accept().await;
+
+    drop(client);
+    drop(fake);
+    let event = tokio::time::timeout(PATIENCE, events.recv()).await.unwrap();
+    assert!(matches!(event, Some(Event::Disconnected)));
+    let end = tokio::time::timeout(PATIENCE, events.recv()).await.unwrap();
+    assert!(end.is_none());
+}
+
+#[tokio::test]
+async fn replies_arrive_while_nobody_reads_events() {
+    let fake = FakeMpv::bind();
+    let mut mpv = Mpv::builder()
+        .event_capacity(2)
+        .command_timeout(PATIENCE)
+        .connect(fake.socket())
+        .await
+        .unwrap();
+    let mut events = mpv.event_receiver.take().unwrap();
+    let mut client = fake.accept().await;
+
+    let server = async {
+        let request = client.request().await.unwrap();
+        for id in 0..50 {
+            let event = json!({"event": "property-change", "id": id, "name": "volume", "data": id});
+            client.send(event).await;
+        }
+        client.reply(&request, json!(49.0)).await;
+    };
+    let (_, volume) = tokio::join!(server, mpv.get_property::<f64>("volume"));
+    assert_eq!(volume.unwrap(), 49.0);
+
+    drop(client);
+    let mut received = Vec::new();
+    while let Some(event) = tokio::time::timeout(PATIENCE, events.recv()).await.unwrap() {
+        received.push(event);
+    }
+    // The queue, the channel and the event being handed over between them.
+    assert!(received.len() <= 4, "{:?}", received);
+    assert!(matches!(
+        received.last(),
+        Some(Event::PropertyChange { id: 49, .. })
+    ));
+}
@@ -0,0 +1,40 @@
+use mpvipc::MpvErrorKind;
+
+#[test]
+fn mpv_error_messages_map_to_their_kind() {
+    let cases = [
+        ("property not found", MpvErrorKind::PropertyNotFound),
+        ("property unavailable", MpvErrorKind::PropertyUnavailable),
+        ("invalid parameter", MpvErrorKind::InvalidParameter),
+        ("error running command", MpvErrorKind::CommandError),
+        (
+            "unsupported format for accessing property",
+            MpvErrorKind::Unsupported,
+        ),
+        (
+            "unsupported format for accessing option",
+            MpvErrorKind::Unsupported,
+        ),
+        ("not supported", MpvErrorKind::Unsupported),
+        ("operation not implemented", MpvErrorKind::Unsupported),
+        ("loading failed", MpvErrorKind::LoadingFailed),
+    ];
+    for (message, kind) in cases.iter() {
+        assert_eq!(&MpvErrorKind::from_mpv_error(message), kind, "{}", message);
+    }
+}
+
+#[test]
+fn other_mpv_errors_keep_their_message() {
+    for message in &[
+        "option not found",
+        "something happened",
+        "not implemented",
+        "",
+    ] {
+        assert_eq!(
+            MpvErrorKind::from_mpv_error(message),
+            MpvErrorKind::Raw(message.to_string())
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use mpvipc::ipc::handle_event;
+use mpvipc::{EndFileReason, Event};
+
+#[test]
+fn start_file_carries_the_playlist_entry() {
+    let event = handle_event(r#"{"event":"start-file","playlist_entry_id":3}"#).unwrap();
+    assert!(matches!(
+        event,
+        Event::StartFile {
+            playlist_entry_id: Some(3)
+        }
+    ));
+}
+
+#[test]
+fn end_file_carries_the_error() {
+    let payload = r#"{"event":"end-file","reason":"error","playlist_entry_id":3,"file_error":"unrecognized file format"}"#;
+    match handle_event(payload).unwrap() {
+        Event::EndFile {
+            reason,
+            playlist_entry_id,
+            file_error,
+            playlist_insert_id,
+            playlist_insert_num_entries,
+        } => {
+            assert_eq!(reason, EndFileReason::Error);
+            assert_eq!(playlist_entry_id, Some(3));
+            assert_eq!(file_error.as_deref(), Some("unrecognized file format"));
+            assert_eq!(playlist_insert_id, None);
+            assert_eq!(playlist_insert_num_entries, None);
+        }
+        other => panic!("{} decoded as {:?}", payload, other),
+    }
+}
+
+#[test]
+fn end_file_carries_the_entries_a_redirect_inserted() {
+    let payload = r#"{"event":"end-file","reason":"redirect","playlist_entry_id":1,"playlist_insert_id":2,"playlist_insert_num_entries":4}"#;
+    match handle_event(payload).unwrap() {
+        Event::EndFile {
+            reason,
+            playlist_entry_id,
+            file_error,
+            playlist_insert_id,
+            playlist_insert_num_entries,
+        } => {
+            assert_eq!(reason, EndFileReason::Redirect);
+            assert_eq!(playlist_entry_id, Some(1));
+            assert_eq!(file_error, None);
+            assert_eq!(playlist_insert_id, Some(2));
+            assert_eq!(playlist_insert_num_entries, Some(4));
+        }
+        other => panic!("{} decoded as {:?}", payload, other),
+    }
+}
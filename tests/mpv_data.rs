@@ -0,0 +1,108 @@
+//! Property-based tests for decoding mpv values into `MpvDataType`.
+
+use mpvipc::ipc::TypeHandler;
+use mpvipc::MpvDataType;
+use proptest::prelude::*;
+use serde_json::{json, Value};
+
+/// Any JSON value mpv could send, with finite numbers only.
+fn json_value() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<u64>().prop_map(Value::from),
+        any::<i64>().prop_map(Value::from),
+        any::<f64>()
+            .prop_filter("JSON numbers are finite", |n| n.is_finite())
+            .prop_map(Value::from),
+        ".*".prop_map(Value::from),
+    ];
+    leaf.prop_recursive(4, 64, 8, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..8).prop_map(Value::Array),
+            prop::collection::hash_map(".*", inner, 0..8)
+                .prop_map(|map| Value::Object(map.into_iter().collect())),
+        ]
+    })
+}
+
+/// Any value the decoder produces: integers are `Usize` unless negative.
+fn mpv_data() -> impl Strategy<Value = MpvDataType> {
+    let leaf = prop_oneof![
+        Just(MpvDataType::Null),
+        any::<bool>().prop_map(MpvDataType::Bool),
+        any::<usize>().prop_map(MpvDataType::Usize),
+        (i64::MIN..0).prop_map(MpvDataType::Int64),
+        any::<f64>()
+            .prop_filter("JSON numbers are finite", |n| n.is_finite())
+            .prop_map(MpvDataType::Double),
+        ".*".prop_map(MpvDataType::String),
+    ];
+    leaf.prop_recursive(4, 64, 8, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..8).prop_map(MpvDataType::Array),
+            prop::collection::hash_map(".*", inner, 0..8).prop_map(MpvDataType::HashMap),
+        ]
+    })
+}
+
+fn decode(value: &Value) -> MpvDataType {
+    MpvDataType::get_value(json!({ "error": "success", "data": value })).unwrap()
+}
+
+proptest! {
+    #[test]
+    fn decoding_keeps_every_value(value in json_value()) {
+        let decoded = decode(&value);
+        prop_assert_eq!(serde_json::to_value(&decoded).unwrap(), value);
+    }
+
+    #[test]
+    fn mixed_arrays_keep_every_element(values in prop::collection::vec(json_value(), 0..16)) {
+        match decode(&Value::Array(values.clone())) {
+            MpvDataType::Array(decoded) => {
+                prop_assert_eq!(decoded.len(), values.len());
+                for (decoded, value) in decoded.iter().zip(&values) {
+                    prop_assert_eq!(decoded, &decode(value));
+                }
+            }
+            other => prop_assert!(false, "decoded as {:?}", other),
+        }
+    }
+
+    #[test]
+    fn serde_round_trips(data in mpv_data()) {
+        let value = serde_json::to_value(&data).unwrap();
+        prop_assert_eq!(&serde_json::from_value::<MpvDataType>(value.clone()).unwrap(), &data);
+        prop_assert_eq!(decode(&value), data);
+    }
+}
+
+#[test]
+fn seekable_ranges_keep_their_types() {
+    let cache_state = json!({
+        "seekable-ranges": [{"start": 1.5, "end": 20}, null, -1, "x"],
+    });
+    let expected = [
+        MpvDataType::HashMap(
+            vec![
+                ("start".to_string(), MpvDataType::Double(1.5)),
+                ("end".to_string(), MpvDataType::Usize(20)),
+            ]
+            .into_iter()
+            .collect(),
+        ),
+        MpvDataType::Null,
+        MpvDataType::Int64(-1),
+        MpvDataType::String("x".to_string()),
+    ];
+    match decode(&cache_state) {
+        MpvDataType::HashMap(map) => {
+            assert_eq!(
+                map["seekable-ranges"],
+                MpvDataType::Array(expected.to_vec())
+            )
+        }
+        other => panic!("decoded as {:?}", other),
+    }
+}
@@ -0,0 +1,172 @@
+//! Payloads mpv can send that don't match what a property usually holds.
+//! None of them may panic; they either decode, fall back to `Property::Unknown` or fail.
+
+use mpvipc::ipc::{handle_event, PlaylistEntry, TypeHandler};
+use mpvipc::{ErrorCode, Event, MpvDataType, Property};
+use serde_json::json;
+use std::collections::HashMap;
+
+const EVENTS: &[&str] = &[
+    r#"{"event":"property-change","id":1,"name":"pause","data":null}"#,
+    r#"{"event":"property-change","id":1,"name":"pause"}"#,
+    r#"{"event":"property-change","id":1,"name":"path","data":42}"#,
+    r#"{"event":"property-change","id":1,"name":"duration","data":"later"}"#,
+    r#"{"event":"property-change","id":1,"name":"metadata","data":{"title":null,"track":-3}}"#,
+    r#"{"event":"property-change","id":1,"name":"metadata","data":[]}"#,
+    r#"{"event":"property-change","id":1,"name":"playlist","data":null}"#,
+    r#"{"event":"property-change","id":1,"name":"playlist","data":[{"filename":null},7]}"#,
+    r#"{"event":"property-change","id":1,"name":"playlist-pos","data":-1}"#,
+    r#"{"event":"property-change","id":1,"name":"playlist-count","data":-1}"#,
+    r#"{"event":"property-change","id":1,"name":"volume","data":-5}"#,
+    r#"{"event":"property-change","id":1,"name":"chapter","data":1.5}"#,
+    r#"{"event":"property-change","id":1,"name":"aid","data":"auto"}"#,
+    r#"{"event":"property-change","id":1,"name":"loop-file","data":-1}"#,
+    r#"{"event":"property-change","id":1,"name":"track-list","data":[null,null]}"#,
+    r#"{"event":"property-change","id":1,"name":"track-list","data":[{"id":1,"ff-index":-1,"lang":null}]}"#,
+    r#"{"event":"property-change","id":1,"name":"demuxer-cache-state","data":{"reader-pts":-0.5,"seekable-ranges":[{"start":-1,"end":null}]}}"#,
+    r#"{"event":"property-change","id":1,"name":"unknown-thing","data":[-1,-2,-3]}"#,
+    r#"{"event":"property-change","id":1,"name":"unknown-thing","data":[[null],{"a":[-1]}]}"#,
+    r#"{"event":"property-change","id":18446744073709551615,"name":"pause","data":true}"#,
+    r#"{"event":"property-change","id":"one","name":"pause","data":true}"#,
+    r#"{"event":"property-change","id":1,"name":null,"data":true}"#,
+    r#"{"event":"end-file","reason":null,"playlist_entry_id":-1}"#,
+    r#"{"event":"start-file","playlist_entry_id":"x"}"#,
+    r#"{"event":"log-message","prefix":1,"level":null}"#,
+    r#"{"event":"client-message","args":"not-a-list"}"#,
+    r#"{"event":"client-message","args":[1,null,"a"]}"#,
+    r#"{"event":"hook","hook_id":-1}"#,
+    r#"{"event":null}"#,
+    r#"{"event":7}"#,
+    r#"{}"#,
+    r#"[]"#,
+    r#"null"#,
+    r#""#,
+    r#"{"event":"property-change""#,
+];
+
+fn property(payload: &str) -> Property {
+    match handle_event(payload) {
+        Ok(Event::PropertyChange { property, .. }) => property,
+        other => panic!("{} decoded as {:?}", payload, other),
+    }
+}
+
+#[test]
+fn odd_events_do_not_panic() {
+    for payload in EVENTS {
+        let _ = handle_event(payload);
+    }
+}
+
+#[test]
+fn unexpected_property_types_fall_back_to_unknown() {
+    match property(r#"{"event":"property-change","id":1,"name":"pause","data":null}"#) {
+        Property::Unknown {
+            name,
+            data: MpvDataType::Null,
+        } => assert_eq!(name, "pause"),
+        other => panic!("unexpected property {:?}", other),
+    }
+    match property(r#"{"event":"property-change","id":1,"name":"duration","data":"later"}"#) {
+        Property::Unknown {
+            data: MpvDataType::String(value),
+            ..
+        } => assert_eq!(value, "later"),
+        other => panic!("unexpected property {:?}", other),
+    }
+}
+
+#[test]
+fn nulls_and_negative_numbers_are_kept_in_maps() {
+    let payload =
+        r#"{"event":"property-change","id":1,"name":"metadata","data":{"title":null,"track":-3}}"#;
+    match property(payload) {
+        Property::Metadata(Some(metadata)) => {
+            assert!(matches!(metadata["title"], MpvDataType::Null));
+            assert!(matches!(metadata["track"], MpvDataType::Int64(-3)));
+        }
+        other => panic!("unexpected property {:?}", other),
+    }
+}
+
+#[test]
+fn arrays_of_nulls_and_negative_numbers_decode() {
+    match property(
+        r#"{"event":"property-change","id":1,"name":"unknown-thing","data":[null,null]}"#,
+    ) {
+        Property::Unknown {
+            data: MpvDataType::Array(values),
+            ..
+        } => assert_eq!(values, [MpvDataType::Null, MpvDataType::Null]),
+        other => panic!("unexpected property {:?}", other),
+    }
+    match property(r#"{"event":"property-change","id":1,"name":"unknown-thing","data":[-1,-2,-3]}"#)
+    {
+        Property::Unknown {
+            data: MpvDataType::Array(values),
+            ..
+        } => assert_eq!(values.len(), 3),
+        other => panic!("unexpected property {:?}", other),
+    }
+}
+
+#[test]
+fn malformed_events_are_errors() {
+    assert!(handle_event(r#"{"event":"property-change","id":1,"name":null,"data":true}"#).is_err());
+    assert!(handle_event(r#"{"event":7}"#).is_err());
+    assert!(matches!(
+        handle_event(r#"{"event":"property-change""#),
+        Err(mpvipc::Error(ErrorCode::JsonParseError(_)))
+    ));
+}
+
+#[test]
+fn type_handlers_reject_odd_replies() {
+    assert!(usize::get_value(json!({"error": "success", "data": -1})).is_err());
+    assert!(usize::get_value(json!({"error": "success", "data": 1.5})).is_err());
+    assert!(usize::get_value(json!({"error": "success", "data": null})).is_err());
+    assert!(f64::get_value(json!({"error": "success", "data": "1.5"})).is_err());
+    assert!(bool::get_value(json!({"error": "success", "data": null})).is_err());
+    assert!(String::get_value(json!({"data": "no error field"})).is_err());
+    assert!(String::get_value(json!(["not", "an", "object"])).is_err());
+    assert!(
+        <HashMap<String, MpvDataType>>::get_value(json!({"error": "success", "data": 3})).is_err()
+    );
+    assert!(<Vec<PlaylistEntry>>::get_value(json!({"error": "success", "data": {}})).is_err());
+
+    let metadata = json!({"error": "success", "data": {"a": null, "b": [-1, null]}});
+    assert!(<HashMap<String, MpvDataType>>::get_value(metadata).is_ok());
+    assert_eq!(
+        f64::get_value(json!({"error": "success", "data": -3})).unwrap(),
+        -3.0
+    );
+}
+
+#[test]
+fn integer_handlers_check_sign_and_range() {
+    assert_eq!(
+        i64::get_value(json!({"error": "success", "data": -1})).unwrap(),
+        -1
+    );
+    assert_eq!(
+        i64::get_value(json!({"error": "success", "data": i64::MIN})).unwrap(),
+        i64::MIN
+    );
+    assert_eq!(
+        u64::get_value(json!({"error": "success", "data": u64::MAX})).unwrap(),
+        u64::MAX
+    );
+    assert_eq!(
+        i32::get_value(json!({"error": "success", "data": -90})).unwrap(),
+        -90
+    );
+    assert_eq!(
+        f32::get_value(json!({"error": "success", "data": 0.5})).unwrap(),
+        0.5
+    );
+    assert!(i32::get_value(json!({"error": "success", "data": i64::MAX})).is_err());
+    assert!(u32::get_value(json!({"error": "success", "data": -1})).is_err());
+    assert!(u64::get_value(json!({"error": "success", "data": -1})).is_err());
+    assert!(i64::get_value(json!({"error": "success", "data": 1.5})).is_err());
+    assert!(i64::get_value(json!({"error": "success", "data": u64::MAX})).is_err());
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use common::{FakeMpv, PATIENCE};
+use mpvipc::{Event, Mpv, ReconnectPolicy};
+use serde_json::json;
+use std::time::Duration;
+
+#[tokio::test]
+async fn observers_are_replayed_after_reconnecting() {
+    let fake = FakeMpv::bind();
+    let mut mpv = Mpv::builder()
+        .reconnect(ReconnectPolicy {
+            initial_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(10),
+            max_attempts: None,
+        })
+        .connect(fake.socket())
+        .await
+        .unwrap();
+    let mut events = mpv.event_receiver.take().unwrap();
+
+    let mut client = fake.accept().await;
+    let server = async {
+        let request = client.request().await.unwrap();
+        client.reply(&request, json!(null)).await;
+    };
+    let (_, observed) = tokio::join!(server, mpv.observe_property(&3, "volume"));
+    observed.unwrap();
+
+    // mpv restarts.
+    drop(client);
+    let mut client = fake.accept().await;
+    let request = client.request().await.unwrap();
+    assert_eq!(request["command"], json!(["observe_property", 3, "volume"]));
+    client.reply(&request, json!(null)).await;
+
+    let event = tokio::time::timeout(PATIENCE, events.recv()).await.unwrap();
+    assert!(matches!(event, Some(Event::Disconnected)));
+    let event = tokio::time::timeout(PATIENCE, events.recv()).await.unwrap();
+    assert!(matches!(event, Some(Event::Reconnected)));
+
+    let server = async {
+        let request = client.request().await.unwrap();
+        client.reply(&request, json!(40.0)).await;
+    };
+    let (_, volume) = tokio::join!(server, mpv.get_property::<f64>("volume"));
+    assert_eq!(volume.unwrap(), 40.0);
+}
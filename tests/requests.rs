@@ -0,0 +1,138 @@
+mod common;
+
+use common::FakeMpv;
+use mpvipc::ipc::PlaylistEntry;
+use mpvipc::{Error, ErrorCode, Mpv, MpvDataType, Playlist};
+use serde_json::json;
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[tokio::test]
+async fn replies_reach_their_caller_in_any_order() {
+    let fake = FakeMpv::bind();
+    let mpv = Mpv::connect(fake.socket()).await.unwrap();
+    let mut client = fake.accept().await;
+
+    let server = async {
+        let first = client.request().await.unwrap();
+        let second = client.request().await.unwrap();
+        // Answer in reverse order, each with the name of the property that was asked for.
+        client.reply(&second, second["command"][1].clone()).await;
+        client.reply(&first, first["command"][1].clone()).await;
+    };
+    let (_, path, title) = tokio::join!(
+        server,
+        mpv.get_property::<String>("path"),
+        mpv.get_property::<String>("media-title"),
+    );
+    assert_eq!(path.unwrap(), "path");
+    assert_eq!(title.unwrap(), "media-title");
+}
+
+#[tokio::test]
+async fn unclaimed_and_late_replies_are_discarded() {
+    let fake = FakeMpv::bind();
+    let mpv = Mpv::connect(fake.socket()).await.unwrap();
+    let mut client = fake.accept().await;
+
+    // The caller gives up before mpv replies.
+    let abandoned =
+        tokio::time::timeout(Duration::from_millis(50), mpv.get_property::<f64>("volume"));
+    let (abandoned, request) = tokio::join!(abandoned, client.request());
+    assert!(abandoned.is_err());
+    let request = request.unwrap();
+    client.reply(&request, json!(50.0)).await;
+    client
+        .send(json!({"request_id": 9999, "error": "success", "data": 1}))
+        .await;
+
+    let server = async {
+        let request = client.request().await.unwrap();
+        assert_eq!(request["command"], json!(["get_property", "speed"]));
+        client.reply(&request, json!(1.5)).await;
+    };
+    let (_, speed) = tokio::join!(server, mpv.get_property::<f64>("speed"));
+    assert_eq!(speed.unwrap(), 1.5);
+}
+
+#[tokio::test]
+async fn get_property_numbers_playlist_entries_by_position() {
+    let fake = FakeMpv::bind();
+    let mpv = Mpv::connect(fake.socket()).await.unwrap();
+    let mut client = fake.accept().await;
+    let playlist = json!([
+        {"id": 7, "filename": "a.mkv", "current": true},
+        {"id": 9, "filename": "b.mkv"},
+    ]);
+
+    let server = async {
+        for _ in 0..3 {
+            let request = client.request().await.unwrap();
+            client.reply(&request, playlist.clone()).await;
+        }
+    };
+    let (_, entries, wrapped, listed) = tokio::join!(
+        server,
+        mpv.get_property::<Vec<PlaylistEntry>>("playlist"),
+        mpv.get_property::<Playlist>("playlist"),
+        mpv.get_playlist(),
+    );
+    let ids = |entries: &[PlaylistEntry]| entries.iter().map(|e| e.id).collect::<Vec<_>>();
+    assert_eq!(ids(&entries.unwrap()), [0, 1]);
+    assert_eq!(ids(&wrapped.unwrap().0), [0, 1]);
+    assert_eq!(ids(&listed.unwrap().0), [0, 1]);
+}
+
+#[tokio::test]
+async fn get_property_mismatches_keep_their_error_code() {
+    let fake = FakeMpv::bind();
+    let mpv = Mpv::connect(fake.socket()).await.unwrap();
+    let mut client = fake.accept().await;
+
+    let server = async {
+        for _ in 0..7 {
+            let request = client.request().await.unwrap();
+            // Everything gets an object, except the map.
+            let data = match request["command"][1].as_str() {
+                Some("metadata") => json!([1]),
+                _ => json!({"a": 1}),
+            };
+            client.reply(&request, data).await;
+        }
+    };
+    let (_, string, boolean, float, index, map, playlist, other) = tokio::join!(
+        server,
+        mpv.get_property::<String>("a"),
+        mpv.get_property::<bool>("b"),
+        mpv.get_property::<f64>("c"),
+        mpv.get_property::<usize>("d"),
+        mpv.get_property::<HashMap<String, MpvDataType>>("metadata"),
+        mpv.get_property::<Vec<PlaylistEntry>>("playlist"),
+        mpv.get_property::<i64>("g"),
+    );
+    assert!(matches!(
+        string,
+        Err(Error(ErrorCode::ValueDoesNotContainString))
+    ));
+    assert!(matches!(
+        boolean,
+        Err(Error(ErrorCode::ValueDoesNotContainBool))
+    ));
+    assert!(matches!(
+        float,
+        Err(Error(ErrorCode::ValueDoesNotContainF64))
+    ));
+    assert!(matches!(
+        index,
+        Err(Error(ErrorCode::ValueDoesNotContainUsize))
+    ));
+    assert!(matches!(
+        map,
+        Err(Error(ErrorCode::ValueDoesNotContainHashMap))
+    ));
+    assert!(matches!(
+        playlist,
+        Err(Error(ErrorCode::ValueDoesNotContainPlaylist))
+    ));
+    assert!(matches!(other, Err(Error(ErrorCode::JsonParseError(_)))));
+}
@@ -0,0 +1,24 @@
+use mpvipc::{parse_timestamp, Error, ErrorCode};
+
+#[test]
+fn timestamps_parse_into_seconds() {
+    assert_eq!(parse_timestamp("01:02:03.5").unwrap(), 3723.5);
+    assert_eq!(parse_timestamp("2:03").unwrap(), 123.0);
+    assert_eq!(parse_timestamp("123.25").unwrap(), 123.25);
+    assert_eq!(parse_timestamp("90:00").unwrap(), 5400.0);
+    assert_eq!(parse_timestamp(" 0:00:07 ").unwrap(), 7.0);
+    assert_eq!(parse_timestamp("100:59:59").unwrap(), 363599.0);
+}
+
+#[test]
+fn malformed_timestamps_are_rejected() {
+    for timestamp in &[
+        "", ":", "1:", ":30", "1:60", "1:00:60", "1:60:00", "-5", "+5", "1.5:00", "1:2:3:4", "1e3",
+        "inf", "NaN", "1.2.3", ".5", "a:00",
+    ] {
+        match parse_timestamp(timestamp) {
+            Err(Error(ErrorCode::InvalidTimestamp(input))) => assert_eq!(&input, timestamp),
+            other => panic!("{:?} parsed as {:?}", timestamp, other),
+        }
+    }
+}
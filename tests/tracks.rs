@@ -0,0 +1,91 @@
+use mpvipc::ipc::handle_event;
+use mpvipc::{Event, Property, Track, TrackType};
+use serde_json::json;
+
+fn track_list() -> serde_json::Value {
+    // Trimmed from what mpv reports for a file with an external audio track.
+    json!([
+        {
+            "id": 1, "type": "video", "src-id": 0, "image": false, "albumart": false,
+            "default": true, "forced": false, "external": false, "selected": true,
+            "main-selection": 0, "ff-index": 0, "codec": "h264",
+            "demux-w": 1920, "demux-h": 1080, "demux-fps": 23.976
+        },
+        {
+            "id": 1, "type": "audio", "src-id": 0, "title": "Dub", "lang": "ger",
+            "image": false, "albumart": false, "default": false, "forced": false,
+            "external": true, "external-filename": "/tmp/dub.flac", "selected": false,
+            "ff-index": 0, "codec": "flac", "demux-channel-count": 6,
+            "demux-channels": "5.1", "demux-samplerate": 48000
+        }
+    ])
+}
+
+#[test]
+fn mpv_track_list_entries_decode() {
+    let tracks: Vec<Track> = serde_json::from_value(track_list()).unwrap();
+    assert_eq!(
+        tracks[0],
+        Track {
+            id: 1,
+            track_type: TrackType::Video,
+            title: None,
+            lang: None,
+            codec: Some("h264".to_string()),
+            default: true,
+            forced: false,
+            selected: true,
+            external: false,
+            external_filename: None,
+            demux_w: Some(1920),
+            demux_h: Some(1080),
+            demux_channel_count: None,
+            demux_channels: None,
+            demux_samplerate: None,
+        }
+    );
+    assert_eq!(
+        tracks[1],
+        Track {
+            id: 1,
+            track_type: TrackType::Audio,
+            title: Some("Dub".to_string()),
+            lang: Some("ger".to_string()),
+            codec: Some("flac".to_string()),
+            default: false,
+            forced: false,
+            selected: false,
+            external: true,
+            external_filename: Some("/tmp/dub.flac".to_string()),
+            demux_w: None,
+            demux_h: None,
+            demux_channel_count: Some(6),
+            demux_channels: Some("5.1".to_string()),
+            demux_samplerate: Some(48000),
+        }
+    );
+}
+
+#[test]
+fn track_list_changes_decode_into_tracks() {
+    let payload = json!({
+        "event": "property-change",
+        "id": 1,
+        "name": "track-list",
+        "data": track_list(),
+    });
+    match handle_event(&payload.to_string()).unwrap() {
+        Event::PropertyChange {
+            property: Property::TrackList(tracks),
+            ..
+        } => {
+            let types: Vec<TrackType> = tracks.iter().map(|track| track.track_type).collect();
+            assert_eq!(types, [TrackType::Video, TrackType::Audio]);
+            assert_eq!(
+                tracks[1].external_filename.as_deref(),
+                Some("/tmp/dub.flac")
+            );
+        }
+        other => panic!("decoded as {:?}", other),
+    }
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::{FakeMpv, PATIENCE};
+use mpvipc::{Error, ErrorCode, Mpv};
+use serde_json::json;
+use tokio_stream::StreamExt;
+
+#[tokio::test]
+async fn property_streams_yield_typed_values_and_unobserve_on_drop() {
+    let fake = FakeMpv::bind();
+    let mpv = Mpv::connect(fake.socket()).await.unwrap();
+    let mut client = fake.accept().await;
+
+    let server = async {
+        let request = client.request().await.unwrap();
+        assert_eq!(
+            request["command"],
+            json!(["observe_property", isize::MAX, "volume"])
+        );
+        client.reply(&request, json!(null)).await;
+        let request = client.request().await.unwrap();
+        assert_eq!(
+            request["command"],
+            json!(["observe_property", isize::MAX - 1, "pause"])
+        );
+        client.reply(&request, json!(null)).await;
+    };
+    let (_, volume, pause) = tokio::join!(
+        server,
+        mpv.watch::<f64>("volume"),
+        mpv.watch::<bool>("pause"),
+    );
+    let (mut volume, pause) = (volume.unwrap(), pause.unwrap());
+    assert_eq!(volume.id(), isize::MAX);
+    assert_eq!(pause.id(), isize::MAX - 1);
+
+    for data in &[json!(50), json!(75.5), json!("loud")] {
+        client
+            .send(json!({
+                "event": "property-change",
+                "id": isize::MAX,
+                "name": "volume",
+                "data": data,
+            }))
+            .await;
+        let value = tokio::time::timeout(PATIENCE, volume.next()).await.unwrap();
+        match data.as_f64() {
+            Some(expected) => assert_eq!(value.unwrap().unwrap(), expected),
+            None => assert!(matches!(
+                value,
+                Some(Err(Error(ErrorCode::ValueDoesNotContainF64)))
+            )),
+        }
+    }
+
+    drop(pause);
+    let request = client.request().await.unwrap();
+    assert_eq!(
+        request["command"],
+        json!(["unobserve_property", isize::MAX - 1])
+    );
+    client.reply(&request, json!(null)).await;
+    let observers = mpv.observers();
+    assert_eq!(observers.len(), 1);
+    assert_eq!(observers[0].property, "volume");
+}